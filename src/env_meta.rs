@@ -15,11 +15,7 @@ pub struct EnvMetadata {
 pub fn collect_env_metadata() -> EnvMetadata {
     let mut meta = EnvMetadata::default();
 
-    if env::var("GITHUB_ACTIONS")
-        .ok()
-        .filter(|v| v == "true" || v == "1")
-        .is_some()
-    {
+    if env::var("GITHUB_ACTIONS").is_ok_and(|v| v == "true" || v == "1") {
         meta.ci = Some("github_actions".into());
         insert(
             &mut meta.entries,
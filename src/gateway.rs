@@ -1,31 +1,240 @@
+#![allow(clippy::missing_errors_doc)]
 use axum::{
-    extract::Path,
-    http::StatusCode,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
 use serde_json::{json, Value};
 
+use crate::httpsig;
+use crate::identity;
 use crate::ledger;
+use crate::ops;
 use crate::receipt;
 use crate::schema;
-use crate::sync::merkle_root;
+use crate::sth;
+use crate::sync::{daemon, peers, reconcile, TrustLevel};
+
+/// Handler error: a status with a plain-text message, or a schema violation
+/// rendered as an RFC 9457 `application/problem+json` body.
+#[derive(Debug)]
+pub enum ApiError {
+    Status(StatusCode, String),
+    Schema(schema::SchemaError),
+}
+
+impl ApiError {
+    #[must_use]
+    pub const fn status(&self) -> StatusCode {
+        match self {
+            Self::Status(status, _) => *status,
+            Self::Schema(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl From<(StatusCode, String)> for ApiError {
+    fn from((status, msg): (StatusCode, String)) -> Self { Self::Status(status, msg) }
+}
+
+impl From<schema::SchemaError> for ApiError {
+    fn from(e: schema::SchemaError) -> Self { Self::Schema(e) }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        match self {
+            Self::Status(status, msg) => (status, msg).into_response(),
+            Self::Schema(e) => {
+                let body = json!({
+                    "type": "https://vaultmesh.dev/problems/schema-violation",
+                    "title": format!("{} schema violation", e.what),
+                    "status": status.as_u16(),
+                    "detail": e.to_string(),
+                    "errors": e.violations,
+                });
+                (status, [(header::CONTENT_TYPE, "application/problem+json")], body.to_string()).into_response()
+            }
+        }
+    }
+}
+
+/// Where an admitted push goes.
+enum Admission {
+    Ingest,
+    /// Quarantined peer: keep the push in the staging area.
+    Stage(String),
+}
+
+/// Gateway settings, shared with the handlers as router state.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Ingest unsigned pushes (local development only).
+    pub allow_unsigned: bool,
+    /// `host[:port]` peers address this node by; signed requests must cover
+    /// it as `@authority`. Without it, signed pushes are refused.
+    pub authority: Option<String>,
+    /// Pull from registered peers this often ([`daemon::run`]).
+    pub sync_interval: Option<std::time::Duration>,
+}
+
+/// File in the ledger dir [`run`] keeps accepted signature nonces in
+/// ([`httpsig::persist_nonces`]).
+const NONCE_FILE: &str = "nonces.jsonl";
+
+/// Authenticate a push and check the signer against the peer registry.
+/// Pushes must carry a valid signature ([`httpsig`], 401 otherwise) by a
+/// registered `did:key` with `full` (ingest) or `quarantine` (staged, never
+/// ingested) trust; other signers get 403. Signatures must cover
+/// [`Options::authority`]. Only [`Options::allow_unsigned`] lets unsigned
+/// pushes through.
+fn admit(opts: &Options, headers: &HeaderMap, path: &str, body: &[u8]) -> Result<Admission, ApiError> {
+    if !headers.contains_key(httpsig::SIGNATURE) {
+        if opts.allow_unsigned {
+            return Ok(Admission::Ingest);
+        }
+        return Err((StatusCode::UNAUTHORIZED, "request is not signed".to_string()).into());
+    }
+    // Never the client's `Host` header: a request signed for another node
+    // would then verify here too.
+    let authority = opts.authority.as_deref().ok_or_else(|| (StatusCode::INTERNAL_SERVER_ERROR, "gateway has no authority configured; signed pushes are refused".to_string()))?;
+    let get = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let id = httpsig::verify("POST", authority, path, body, get)
+        .map_err(|e| (StatusCode::UNAUTHORIZED, e.to_string()))?;
+    let peer = peers::get(&id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::FORBIDDEN, format!("unknown peer {id}")))?;
+    match peer.trust {
+        TrustLevel::Full => Ok(Admission::Ingest),
+        TrustLevel::ReadOnly => Err((StatusCode::FORBIDDEN, format!("peer {id} is read-only")).into()),
+        TrustLevel::Quarantine => Ok(Admission::Stage(peer.id)),
+    }
+}
+
+/// Run `f`, which reads or writes the ledger, on the blocking thread pool so
+/// ledger I/O and `ledger.lock` waits do not stall the async workers.
+async fn blocking<T, E>(f: impl FnOnce() -> Result<T, E> + Send + 'static) -> Result<T, E>
+where
+    T: Send + 'static,
+    E: From<(StatusCode, String)> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await.unwrap_or_else(|e| Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into()))
+}
+
+fn parse_json(body: &[u8]) -> Result<Value, ApiError> {
+    Ok(serde_json::from_slice(body).map_err(|e| (StatusCode::BAD_REQUEST, format!("invalid JSON body: {e}")))?)
+}
 
 pub async fn health() -> &'static str { "ok" }
 
 pub async fn get_receipt(Path(digest): Path<String>) -> Result<String, (StatusCode, String)> {
-    let data = ledger::get_json(&digest).map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    let data = blocking(move || ledger::get_json(&digest).map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))).await?;
     Ok(String::from_utf8_lossy(&data).into_owned())
 }
 
+/// Largest page `GET /v1/ledger` returns.
+pub const MAX_PAGE: usize = 1000;
+
+#[derive(serde::Deserialize)]
+pub struct ListQuery {
+    #[serde(default)]
+    pub since: u64,
+    pub limit: Option<usize>,
+}
+
+/// GET /v1/ledger?since=<seq>&limit=<n>
+/// Live index entries with `seq >= since`, in ingest order, at most `limit`
+/// (default and cap [`MAX_PAGE`]). `next` is the cursor for the following page.
+pub async fn list_ledger(Query(q): Query<ListQuery>) -> Result<Json<Value>, ApiError> {
+    let limit = q.limit.unwrap_or(MAX_PAGE).clamp(1, MAX_PAGE);
+    let mut entries: Vec<ledger::Entry> = blocking(|| ledger::list().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())))
+        .await?
+        .into_iter()
+        .filter(|e| e.seq >= q.since)
+        .collect();
+    let more = entries.len() > limit;
+    entries.truncate(limit);
+    let next = entries.last().map_or(q.since, |e| e.seq + 1);
+    Ok(Json(json!({ "entries": entries, "next": next, "more": more })))
+}
+
+#[derive(serde::Deserialize)]
+pub struct RangesQuery {
+    /// Comma-separated hex digest prefixes; empty for the whole ledger.
+    #[serde(default)]
+    pub prefixes: String,
+}
+
+/// GET /v1/ledger/ranges?prefixes=<hex>,<hex>
+/// Count and fingerprint of the live digests under each prefix, with the
+/// entries of small ranges, for anti-entropy ([`crate::sync::reconcile`]).
+pub async fn get_ranges(Query(q): Query<RangesQuery>) -> Result<Json<Value>, ApiError> {
+    let prefixes: Vec<String> = q.prefixes.split(',').map(str::to_string).collect();
+    let sorted = blocking(|| ledger::sorted_by_digest().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))).await?;
+    let ranges = reconcile::ranges(&sorted, &prefixes)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(Json(json!({ "ranges": ranges })))
+}
+
+/// GET /v1/proof/:digest
+/// Returns the current ledger root, tree size and audit path for a stored receipt.
+pub async fn get_proof(Path(digest): Path<String>) -> Result<Json<ledger::InclusionProof>, (StatusCode, String)> {
+    blocking(move || {
+        ledger::inclusion_proof(&digest)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .map(Json)
+            .ok_or_else(|| (StatusCode::NOT_FOUND, format!("no receipt with digest {digest}")))
+    })
+    .await
+}
+
+#[derive(serde::Deserialize)]
+pub struct ConsistencyQuery {
+    pub old: usize,
+    pub new: usize,
+}
+
+/// GET /v1/consistency?old=<n>&new=<n>
+/// Consistency proof between this node's tree at `old` and at `new` leaves,
+/// so a peer holding an earlier tree head can check the log only grew.
+pub async fn get_consistency(Query(q): Query<ConsistencyQuery>) -> Result<Json<Value>, ApiError> {
+    let (old, new) = (q.old, q.new);
+    let proof = blocking(move || ledger::consistency_proof(old, new).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))).await?;
+    Ok(Json(json!({ "old_size": q.old, "new_size": q.new, "proof": proof })))
+}
+
+/// GET /v1/sth
+/// Returns the current ledger root and size signed with this node's actor key.
+pub async fn get_sth() -> Result<Json<sth::SignedTreeHead>, (StatusCode, String)> {
+    blocking(signed_tree_head).await
+}
+
+fn signed_tree_head() -> Result<Json<sth::SignedTreeHead>, (StatusCode, String)> {
+    let (root, size) = ledger::merkle_root().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let kp = identity::load_actor_keypair().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let head = sth::sign(ledger::TREE_ALG, size as u64, &root, &kp)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(head))
+}
+
 /// POST /v1/verify
 /// body: { receipt: Receipt, provenance: Provenance }
-pub async fn post_verify(Json(body): Json<Value>) -> Result<Json<Value>, (StatusCode, String)> {
-    let r_val = body.get("receipt").cloned().ok_or((StatusCode::BAD_REQUEST, "missing receipt".to_string()))?;
-    let p_val = body.get("provenance").cloned().ok_or((StatusCode::BAD_REQUEST, "missing provenance".to_string()))?;
+pub async fn post_verify(State(opts): State<Options>, headers: HeaderMap, body: Bytes) -> Result<Json<Value>, ApiError> {
+    blocking(move || ingest_bundle(&opts, &headers, &body)).await
+}
+
+fn ingest_bundle(opts: &Options, headers: &HeaderMap, body: &[u8]) -> Result<Json<Value>, ApiError> {
+    let admission = admit(opts, headers, "/v1/verify", body)?;
+    let body = parse_json(body)?;
+    let r_val = body.get("receipt").cloned().ok_or_else(|| (StatusCode::BAD_REQUEST, "missing receipt".to_string()))?;
+    let p_val = body.get("provenance").cloned().ok_or_else(|| (StatusCode::BAD_REQUEST, "missing provenance".to_string()))?;
 
-    schema::validate_receipt(&r_val).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
-    schema::validate_provenance(&p_val).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    schema::validate_receipt(&r_val)?;
+    schema::validate_provenance(&p_val)?;
 
     let rcpt: crate::receipt::Receipt = serde_json::from_value(r_val.clone()).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
     receipt::verify_receipt(&rcpt).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
@@ -35,32 +244,94 @@ pub async fn post_verify(Json(body): Json<Value>) -> Result<Json<Value>, (Status
     let r_bytes = serde_json::to_vec(&r_val).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     let p_bytes = serde_json::to_vec(&p_val).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    let receipt_digest = ledger::add_json("receipt", &r_bytes, commit, git_ref)
+    if let Admission::Stage(peer) = admission {
+        let receipt_digest = ledger::staging::stage("receipt", &r_bytes, &peer)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        ledger::staging::stage("provenance", &p_bytes, &peer)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        return Ok(Json(json!({ "status": "staged", "receipt_digest": receipt_digest, "peer": peer })));
+    }
+
+    let receipt_digest = ledger::add_json("receipt", &r_bytes, commit.clone(), git_ref.clone())
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    let _prov_digest = ledger::add_json("provenance", &p_bytes, None, None)
+    let _prov_digest = ledger::add_json("provenance", &p_bytes, commit, git_ref)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    let mut digests: Vec<String> = ledger::list()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        .into_iter()
-        .filter(|e| e.kind == "receipt")
-        .map(|e| e.digest)
-        .collect();
-    digests.sort();
-    let merkle = merkle_root(&digests);
+    let (root, count) = ledger::merkle_root().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(json!({
         "status": "verified",
         "receipt_digest": receipt_digest,
-        "merkle_root": merkle
+        "merkle_root": root,
+        "alg": ledger::TREE_ALG,
+        "count": count
     })))
 }
 
-pub async fn run(addr: &str) -> anyhow::Result<()> {
+/// POST /v1/receipts/ops
+/// body: ops Receipt (Terraform plan/apply), signed and with its leaf set
+pub async fn post_ops_receipt(State(opts): State<Options>, headers: HeaderMap, body: Bytes) -> Result<Json<Value>, ApiError> {
+    blocking(move || ingest_ops(&opts, &headers, &body)).await
+}
+
+fn ingest_ops(opts: &Options, headers: &HeaderMap, body: &[u8]) -> Result<Json<Value>, ApiError> {
+    let admission = admit(opts, headers, "/v1/receipts/ops", body)?;
+    let body = parse_json(body)?;
+    schema::validate_ops_receipt(&body)?;
+    let rec: ops::Receipt = serde_json::from_value(body).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    rec.verify_for_ingest().map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+
+    if let Admission::Stage(peer) = admission {
+        let bytes = serde_json::to_vec(&rec).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        let receipt_digest = ledger::staging::stage(ledger::OPS, &bytes, &peer)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        return Ok(Json(json!({ "status": "staged", "receipt_digest": receipt_digest, "leaf": rec.leaf, "peer": peer })));
+    }
+
+    let receipt_digest = ledger::add_ops(&rec).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let (root, count) = ledger::merkle_root().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(json!({
+        "status": "verified",
+        "receipt_digest": receipt_digest,
+        "leaf": rec.leaf,
+        "merkle_root": root,
+        "alg": ledger::TREE_ALG,
+        "count": count
+    })))
+}
+
+/// GET /v1/peers
+/// Registered peers with their sync health, and the last round's error.
+///
+/// Per peer: last sync, lag in time and in leaves, and last error; the round
+/// error is set if the last background sync round failed as a whole.
+/// Unauthenticated on purpose, like the other GET endpoints: peer DIDs and URLs are what nodes exchange to sync, and the
+/// ledger they sync is itself served to anyone. Keep the gateway off public
+/// networks if the registry must stay private.
+pub async fn get_peers() -> Result<Json<daemon::SyncHealth>, (StatusCode, String)> {
+    blocking(|| daemon::health().map(Json).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))).await
+}
+
+/// Serve the gateway on `addr`; with `opts.sync_interval`, also pull from
+/// registered peers in the background ([`daemon::run`]).
+pub async fn run(addr: &str, opts: Options) -> anyhow::Result<()> {
+    httpsig::persist_nonces(&ledger::ledger_dir()?.join(NONCE_FILE))?;
     let app = Router::new()
         .route("/v1/health", get(health))
+        .route("/v1/ledger", get(list_ledger))
+        .route("/v1/ledger/ranges", get(get_ranges))
         .route("/v1/ledger/:digest", get(get_receipt))
-        .route("/v1/verify", post(post_verify));
+        .route("/v1/proof/:digest", get(get_proof))
+        .route("/v1/sth", get(get_sth))
+        .route("/v1/consistency", get(get_consistency))
+        .route("/v1/verify", post(post_verify))
+        .route("/v1/receipts/ops", post(post_ops_receipt))
+        .route("/v1/peers", get(get_peers))
+        .with_state(opts.clone());
+    if let Some(interval) = opts.sync_interval {
+        tokio::spawn(daemon::run(interval));
+    }
     let listener = tokio::net::TcpListener::bind(addr).await?;
     axum::serve(listener, app).await?;
     Ok(())
@@ -0,0 +1,329 @@
+#![allow(clippy::missing_errors_doc, clippy::must_use_candidate)]
+//! HTTP Message Signatures (RFC 9421) for requests between peers.
+//!
+//! A peer signs the method, authority, path and `Content-Digest` (RFC 9530,
+//! `sha-256`) of each request with its ed25519 actor key; `keyid` is the
+//! peer's `did:key` ([`crate::identity::did_key_from_public`]), so the gateway
+//! can check the signature without a key lookup and then match the DID against
+//! the peer registry. Only one signature, labelled `sig1`, is produced or
+//! accepted.
+//!
+//! Each signature carries a random `nonce`. [`verify`] remembers the nonces it
+//! accepted while their `created` time is inside the skew window, so a
+//! captured request cannot be replayed to the same node, and covering
+//! `@authority` keeps it from being replayed to another one. A node that calls
+//! [`persist_nonces`] also keeps them across restarts.
+use crate::identity::{did_key_from_public, public_from_did_key};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::{Keypair, Signature, Signer, Verifier};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const CONTENT_DIGEST: &str = "content-digest";
+pub const SIGNATURE_INPUT: &str = "signature-input";
+pub const SIGNATURE: &str = "signature";
+
+const LABEL: &str = "sig1";
+const COMPONENTS: [&str; 4] = ["@method", "@authority", "@path", CONTENT_DIGEST];
+
+/// Largest accepted difference between `created` and the verifier's clock.
+pub const MAX_SKEW_SECS: i64 = 300;
+
+/// Most nonces remembered at once; past this, signed requests are refused
+/// until older nonces leave the skew window.
+pub const MAX_NONCES: usize = 100_000;
+
+/// Accepted nonces, and the file they are appended to, if any.
+#[derive(Default)]
+struct Seen {
+    /// `(keyid, nonce)` pairs and their `created` time.
+    nonces: HashMap<(String, String), i64>,
+    log: Option<PathBuf>,
+    /// Lines in `log`; it is rewritten with only live nonces once they are
+    /// mostly expired.
+    lines: usize,
+}
+
+static SEEN: Mutex<Option<Seen>> = Mutex::new(None);
+
+/// One line of the nonce log.
+#[derive(Serialize, Deserialize)]
+struct SeenLine {
+    keyid: String,
+    nonce: String,
+    created: i64,
+}
+
+impl Seen {
+    /// Drop nonces whose `created` time has left the skew window: [`verify`]
+    /// refuses their requests as stale anyway.
+    fn expire(&mut self) {
+        let oldest = chrono::Utc::now().timestamp() - MAX_SKEW_SECS;
+        self.nonces.retain(|_, t| *t >= oldest);
+    }
+
+    /// Rewrite the log with the live nonces only.
+    fn compact(&mut self) -> Result<()> {
+        let Some(path) = &self.log else {
+            return Ok(());
+        };
+        let mut out = Vec::new();
+        for ((keyid, nonce), created) in &self.nonces {
+            out.extend(serde_json::to_vec(&SeenLine {
+                keyid: keyid.clone(),
+                nonce: nonce.clone(),
+                created: *created,
+            })?);
+            out.push(b'\n');
+        }
+        let tmp = path.with_extension("jsonl.tmp");
+        std::fs::write(&tmp, out)?;
+        std::fs::rename(tmp, path)?;
+        self.lines = self.nonces.len();
+        Ok(())
+    }
+
+    fn insert(&mut self, keyid: &str, nonce: &str, created: i64) -> Result<()> {
+        if let Some(path) = &self.log {
+            let mut line = serde_json::to_vec(&SeenLine {
+                keyid: keyid.to_string(),
+                nonce: nonce.to_string(),
+                created,
+            })?;
+            line.push(b'\n');
+            let mut f = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            f.write_all(&line)?;
+            f.sync_data()?;
+            self.lines += 1;
+        }
+        self.nonces
+            .insert((keyid.to_string(), nonce.to_string()), created);
+        if self.lines > 2 * self.nonces.len() + 1024 {
+            self.expire();
+            self.compact()?;
+        }
+        Ok(())
+    }
+}
+
+/// Append accepted nonces to `path` from now on.
+///
+/// The ones it already holds that are still inside the skew window are
+/// loaded first, so that a restarted node keeps refusing requests it
+/// accepted before. Without it, nonces are only kept in memory.
+pub fn persist_nonces(path: &Path) -> Result<()> {
+    let data = match std::fs::read(path) {
+        Ok(d) => d,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let mut guard = SEEN
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let seen = guard.get_or_insert_with(Seen::default);
+    // A line torn by a crash mid-append is skipped; its request was never
+    // answered.
+    for n in data
+        .split(|b| *b == b'\n')
+        .filter_map(|l| serde_json::from_slice::<SeenLine>(l).ok())
+    {
+        seen.nonces.insert((n.keyid, n.nonce), n.created);
+    }
+    seen.log = Some(path.to_path_buf());
+    seen.expire();
+    let compacted = seen.compact();
+    drop(guard);
+    compacted
+}
+
+/// Header values to attach to a signed request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedHeaders {
+    pub content_digest: String,
+    pub signature_input: String,
+    pub signature: String,
+}
+
+impl SignedHeaders {
+    /// `(name, value)` pairs, for HTTP clients.
+    pub fn pairs(&self) -> [(&'static str, &str); 3] {
+        [
+            (CONTENT_DIGEST, &self.content_digest),
+            (SIGNATURE_INPUT, &self.signature_input),
+            (SIGNATURE, &self.signature),
+        ]
+    }
+}
+
+pub fn content_digest(body: &[u8]) -> String {
+    format!(
+        "sha-256=:{}:",
+        general_purpose::STANDARD.encode(Sha256::digest(body))
+    )
+}
+
+/// Sign a request to `authority` (`host[:port]`) at the current time.
+///
+/// # Panics
+/// If the OS random number generator fails to produce a nonce.
+pub fn sign(method: &str, authority: &str, path: &str, body: &[u8], kp: &Keypair) -> SignedHeaders {
+    sign_at(method, authority, path, body, kp, chrono::Utc::now().timestamp())
+}
+
+/// [`sign`] at `created` (Unix seconds) rather than the current time.
+///
+/// # Panics
+/// If the OS random number generator fails to produce a nonce.
+pub fn sign_at(
+    method: &str,
+    authority: &str,
+    path: &str,
+    body: &[u8],
+    kp: &Keypair,
+    created: i64,
+) -> SignedHeaders {
+    let content_digest = content_digest(body);
+    let mut nonce = [0u8; 16];
+    getrandom::getrandom(&mut nonce).expect("getrandom failed");
+    let params = format!(
+        "({});created={created};nonce=\"{}\";keyid=\"{}\";alg=\"ed25519\"",
+        COMPONENTS.map(|c| format!("\"{c}\"")).join(" "),
+        general_purpose::URL_SAFE_NO_PAD.encode(nonce),
+        did_key_from_public(kp.public.as_bytes())
+    );
+    let base = signature_base(method, authority, path, &content_digest, &params);
+    let sig: Signature = kp.sign(base.as_bytes());
+    SignedHeaders {
+        content_digest,
+        signature_input: format!("{LABEL}={params}"),
+        signature: format!(
+            "{LABEL}=:{}:",
+            general_purpose::STANDARD.encode(sig.to_bytes())
+        ),
+    }
+}
+
+fn signature_base(method: &str, authority: &str, path: &str, content_digest: &str, params: &str) -> String {
+    format!(
+        "\"@method\": {}\n\"@authority\": {}\n\"@path\": {path}\n\"{CONTENT_DIGEST}\": {content_digest}\n\"@signature-params\": {params}",
+        method.to_ascii_uppercase(),
+        authority.to_ascii_lowercase()
+    )
+}
+
+/// Verify a signed request to `authority` and return the signer's `did:key`.
+///
+/// `header` looks up a request header by lowercase name. A request whose
+/// nonce was already accepted within the skew window is refused.
+pub fn verify<'a>(
+    method: &str,
+    authority: &str,
+    path: &str,
+    body: &[u8],
+    header: impl Fn(&str) -> Option<&'a str>,
+) -> Result<String> {
+    let get = |name: &str| header(name).ok_or_else(|| anyhow!("missing {name} header"));
+    let digest = get(CONTENT_DIGEST)?;
+    if digest != content_digest(body) {
+        return Err(anyhow!("{CONTENT_DIGEST} does not match the body"));
+    }
+
+    let params = get(SIGNATURE_INPUT)?
+        .strip_prefix(&format!("{LABEL}="))
+        .ok_or_else(|| anyhow!("{SIGNATURE_INPUT} has no {LABEL}"))?;
+    let (covered, rest) = params
+        .strip_prefix('(')
+        .and_then(|p| p.split_once(')'))
+        .ok_or_else(|| anyhow!("malformed {SIGNATURE_INPUT}"))?;
+    let covered: Vec<&str> = covered
+        .split_whitespace()
+        .map(|c| c.trim_matches('"'))
+        .collect();
+    if covered != COMPONENTS {
+        return Err(anyhow!("signature must cover {}", COMPONENTS.join(", ")));
+    }
+    let (mut created, mut nonce, mut keyid) = (None, None, None);
+    for param in rest.split(';').filter(|p| !p.is_empty()) {
+        let (k, v) = param
+            .split_once('=')
+            .ok_or_else(|| anyhow!("malformed {SIGNATURE_INPUT} parameter {param}"))?;
+        match (k, v.trim_matches('"')) {
+            ("created", v) => created = Some(v.parse::<i64>()?),
+            ("nonce", v) => nonce = Some(v),
+            ("keyid", v) => keyid = Some(v),
+            ("alg", "ed25519") => {}
+            ("alg", other) => return Err(anyhow!("unsupported signature alg {other}")),
+            _ => {}
+        }
+    }
+    let created = created.ok_or_else(|| anyhow!("signature has no created time"))?;
+    if (chrono::Utc::now().timestamp() - created).abs() > MAX_SKEW_SECS {
+        return Err(anyhow!(
+            "signature created {created} is outside the accepted window"
+        ));
+    }
+    let nonce = nonce.ok_or_else(|| anyhow!("signature has no nonce"))?;
+    let keyid = keyid.ok_or_else(|| anyhow!("signature has no keyid"))?;
+    let pk = public_from_did_key(keyid)?;
+
+    let sig_b64 = get(SIGNATURE)?
+        .strip_prefix(&format!("{LABEL}=:"))
+        .and_then(|s| s.strip_suffix(':'))
+        .ok_or_else(|| anyhow!("malformed {SIGNATURE} header"))?;
+    let sig_bytes = general_purpose::STANDARD
+        .decode(sig_b64)
+        .map_err(|e| anyhow!("bad signature b64: {e}"))?;
+    let sig = Signature::from_bytes(&sig_bytes).map_err(|e| anyhow!("bad signature: {e}"))?;
+    let base = signature_base(method, authority, path, digest, params);
+    pk.verify(base.as_bytes(), &sig)
+        .map_err(|_| anyhow!("request signature verification failed"))?;
+    remember(keyid, nonce, created)?;
+    Ok(keyid.to_string())
+}
+
+/// Record an accepted nonce, refusing one seen before.
+fn remember(keyid: &str, nonce: &str, created: i64) -> Result<()> {
+    let mut guard = SEEN
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let seen = guard.get_or_insert_with(Seen::default);
+    if seen
+        .nonces
+        .contains_key(&(keyid.to_string(), nonce.to_string()))
+    {
+        return Err(anyhow!("signature nonce {nonce} was already used"));
+    }
+    if seen.nonces.len() >= MAX_NONCES {
+        seen.expire();
+        if seen.nonces.len() >= MAX_NONCES {
+            return Err(anyhow!("too many signed requests; retry later"));
+        }
+    }
+    let inserted = seen.insert(keyid, nonce, created);
+    drop(guard);
+    inserted
+}
+
+/// Authority (`host[:port]`) of `url`, for signing a client request.
+pub fn authority_of(url: &str) -> &str {
+    let after_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    after_scheme
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or(after_scheme)
+}
+
+/// Path component of `url`, for signing a client request.
+pub fn path_of(url: &str) -> &str {
+    let after_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let path = after_scheme.find('/').map_or("/", |i| &after_scheme[i..]);
+    path.split(['?', '#']).next().unwrap_or("/")
+}
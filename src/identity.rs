@@ -225,7 +225,8 @@ fn actor_key_path() -> Result<PathBuf> {
     Ok(dir)
 }
 
-fn did_key_from_public(public_key: &[u8]) -> String {
+#[must_use]
+pub fn did_key_from_public(public_key: &[u8]) -> String {
     let mut data = Vec::with_capacity(MULTICODEC_ED25519_PREFIX.len() + public_key.len());
     data.extend_from_slice(&MULTICODEC_ED25519_PREFIX);
     data.extend_from_slice(public_key);
@@ -233,6 +234,21 @@ fn did_key_from_public(public_key: &[u8]) -> String {
     format!("did:key:z{encoded}")
 }
 
+/// Recover the ed25519 public key from a `did:key:z...` identifier.
+#[allow(clippy::missing_errors_doc)]
+pub fn public_from_did_key(did: &str) -> Result<PublicKey> {
+    let encoded = did
+        .strip_prefix("did:key:z")
+        .ok_or_else(|| anyhow!("not a base58btc did:key: {did}"))?;
+    let data = bs58::decode(encoded)
+        .into_vec()
+        .map_err(|e| anyhow!("invalid did:key encoding: {e}"))?;
+    let key = data
+        .strip_prefix(&MULTICODEC_ED25519_PREFIX)
+        .ok_or_else(|| anyhow!("did:key is not an ed25519 key: {did}"))?;
+    PublicKey::from_bytes(key).map_err(|e| anyhow!("invalid did:key public key: {e}"))
+}
+
 fn non_empty_trimmed<S: Into<String>>(input: S) -> Option<String> {
     let s = input.into().trim().to_string();
     if s.is_empty() {
@@ -276,6 +292,15 @@ mod tests {
         assert!(did.starts_with("did:key:z"));
     }
 
+    #[test]
+    fn test_did_key_round_trip() {
+        let secret = SecretKey::from_bytes(&[7u8; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        let did = did_key_from_public(public.as_bytes());
+        assert_eq!(public_from_did_key(&did).unwrap(), public);
+        assert!(public_from_did_key("did:web:example.com").is_err());
+    }
+
     #[test]
     fn test_non_empty_trimmed() {
         assert_eq!(non_empty_trimmed(" test "), Some("test".into()));
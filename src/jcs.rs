@@ -0,0 +1,160 @@
+#![allow(clippy::missing_errors_doc, clippy::must_use_candidate)]
+//! RFC 8785 JSON Canonicalization Scheme (JCS).
+//!
+//! Object members are sorted by their UTF-16 code units, strings use the
+//! minimal JSON escapes, and numbers are printed the way ECMAScript's
+//! `Number.prototype.toString` does. This is the byte form hashed for ledger
+//! digests so the same document gets the same digest on every ingest path.
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::{Number, Value};
+use std::fmt::Write as _;
+
+/// Canonical bytes of a JSON value.
+pub fn to_vec(v: &Value) -> Vec<u8> {
+    let mut out = String::new();
+    write_value(v, &mut out);
+    out.into_bytes()
+}
+
+/// Canonical bytes of any serializable value.
+pub fn to_vec_from<T: Serialize>(t: &T) -> Result<Vec<u8>> {
+    Ok(to_vec(&serde_json::to_value(t)?))
+}
+
+/// Parse `bytes` as JSON and return their canonical form.
+pub fn canonicalize(bytes: &[u8]) -> Result<Vec<u8>> {
+    let v: Value = serde_json::from_slice(bytes).map_err(|e| anyhow!("not JSON: {e}"))?;
+    Ok(to_vec(&v))
+}
+
+fn write_value(v: &Value, out: &mut String) {
+    match v {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => write_number(n, out),
+        Value::String(s) => write_string(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut members: Vec<(&String, &Value)> = map.iter().collect();
+            members.sort_by(|a, b| a.0.encode_utf16().cmp(b.0.encode_utf16()));
+            out.push('{');
+            for (i, (k, val)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(k, out);
+                out.push(':');
+                write_value(val, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{08}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{0c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// ECMAScript number serialization (RFC 8785 §3.2.2.3).
+fn write_number(n: &Number, out: &mut String) {
+    // Every JSON number is an IEEE-754 double in JCS, so integers beyond 2^53
+    // round the same way they would in ECMAScript.
+    let f = n.as_f64().unwrap_or_default();
+    if f == 0.0 {
+        out.push('0');
+        return;
+    }
+    let abs = f.abs();
+    if (1e-6..1e21).contains(&abs) {
+        // Rust's shortest round-trip Display matches ES in this range.
+        let _ = write!(out, "{f}");
+        return;
+    }
+    let sci = format!("{f:e}");
+    let (mantissa, exp) = sci.split_once('e').unwrap_or((&sci, "0"));
+    out.push_str(mantissa);
+    out.push('e');
+    if !exp.starts_with('-') {
+        out.push('+');
+    }
+    out.push_str(exp);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn canon(v: &Value) -> String {
+        String::from_utf8(to_vec(v)).unwrap()
+    }
+
+    #[test]
+    fn sorts_members_and_drops_whitespace() {
+        let bytes = br#"{ "b": [1, 2, {"z": null, "a": true}], "a": "x" }"#;
+        assert_eq!(
+            String::from_utf8(canonicalize(bytes).unwrap()).unwrap(),
+            r#"{"a":"x","b":[1,2,{"a":true,"z":null}]}"#
+        );
+    }
+
+    #[test]
+    fn sorts_by_utf16_code_units() {
+        // U+1F600 (surrogate pair D83D..) sorts before U+FB33 in UTF-16 but after it in UTF-8.
+        let v = json!({"\u{fb33}": 1, "\u{1f600}": 2});
+        assert_eq!(canon(&v), "{\"\u{1f600}\":2,\"\u{fb33}\":1}");
+    }
+
+    #[test]
+    fn escapes_minimally() {
+        let v = json!("a\"b\\c\n\u{1}/é");
+        assert_eq!(canon(&v), "\"a\\\"b\\\\c\\n\\u0001/é\"");
+    }
+
+    #[test]
+    fn numbers_follow_ecmascript() {
+        let cases = [
+            ("0", "0"),
+            ("-0.0", "0"),
+            ("1.0", "1"),
+            ("100", "100"),
+            ("-1.5", "-1.5"),
+            ("1e21", "1e+21"),
+            ("1e20", "100000000000000000000"),
+            ("0.000001", "0.000001"),
+            ("1e-7", "1e-7"),
+            ("1.25e-7", "1.25e-7"),
+            ("333333333.33333329", "333333333.3333333"),
+        ];
+        for (input, expected) in cases {
+            let v: Value = serde_json::from_str(input).unwrap();
+            assert_eq!(canon(&v), expected, "{input}");
+        }
+    }
+}
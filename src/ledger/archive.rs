@@ -0,0 +1,249 @@
+#![allow(clippy::missing_errors_doc)]
+//! Portable ledger archives for moving objects between air-gapped nodes.
+//!
+//! An archive is a plain tar file holding:
+//! - `manifest.json`: signed [`Manifest`] binding the object root, count and index
+//! - `index.jsonl`: the exported index entries, in ingest order
+//! - `objects/<digest>.json`: one canonical object per entry
+//!
+//! Import checks the manifest signature and signer, the index digest, the
+//! root, and every object's digest, schema and signature before anything is
+//! written to the local ledger.
+use super::{Entry, TREE_ALG};
+use crate::identity::{did_key_from_public, public_from_did_key};
+use crate::merkle::Alg;
+use crate::receipt::{blake3_hex, hash_canonical};
+use crate::sync::TrustLevel;
+use anyhow::{anyhow, Result};
+use base64::Engine as _;
+use ed25519_dalek::{Keypair, Signature, Signer, Verifier};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::Path;
+
+const MANIFEST: &str = "manifest.json";
+const INDEX: &str = "index.jsonl";
+const OBJECTS: &str = "objects/";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Manifest {
+    pub version: u32,
+    /// RFC3339 time the archive was written.
+    pub created: String,
+    /// Only entries ingested at or after this time were exported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    /// Tree over the exported digests in index order.
+    pub alg: Alg,
+    pub root: String,
+    pub count: u64,
+    /// BLAKE3 of `index.jsonl`.
+    pub index: String,
+    /// `did:key` of the exporting node.
+    pub node: String,
+    /// Base64 ed25519 signature over the canonical hash of the other fields.
+    pub sig: String,
+}
+
+impl Manifest {
+    /// Check the signature against the key embedded in `node`.
+    pub fn verify(&self) -> Result<()> {
+        let pk = public_from_did_key(&self.node)?;
+        let sig_bytes = base64::engine::general_purpose::STANDARD
+            .decode(self.sig.as_bytes())
+            .map_err(|e| anyhow!("bad manifest signature b64: {e}"))?;
+        let sig = Signature::from_bytes(&sig_bytes)
+            .map_err(|e| anyhow!("bad manifest signature: {e}"))?;
+        pk.verify(signing_digest(self)?.as_bytes(), &sig)
+            .map_err(|e| anyhow!("manifest signature verify failed: {e}"))
+    }
+}
+
+fn signing_digest(m: &Manifest) -> Result<String> {
+    let mut v = serde_json::to_value(m)?;
+    if let Value::Object(ref mut map) = v {
+        map.remove("sig");
+    }
+    Ok(hash_canonical(&v))
+}
+
+/// Write every live entry ingested at or after `since` (all when `None`) and
+/// its object to a tar archive at `out`, signing the manifest with `kp`.
+///
+/// Objects of no known kind are left out, and out of the manifest's root and
+/// count: [`import`] classifies every object and would refuse the archive.
+pub fn export(
+    out: &Path,
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    kp: &Keypair,
+) -> Result<Manifest> {
+    let mut entries = Vec::new();
+    let mut objects = Vec::new();
+    for e in super::list()? {
+        let recent = since.is_none_or(|cutoff| {
+            chrono::DateTime::parse_from_rfc3339(&e.ts).is_ok_and(|ts| ts >= cutoff)
+        });
+        if !recent {
+            continue;
+        }
+        let bytes = super::get_json(&e.digest)?;
+        if super::classify(&bytes) == "unknown" {
+            continue;
+        }
+        entries.push(e);
+        objects.push(bytes);
+    }
+    let mut index = Vec::new();
+    for e in &entries {
+        index.extend(serde_json::to_vec(e)?);
+        index.push(b'\n');
+    }
+    let digests: Vec<String> = entries.iter().map(|e| e.digest.clone()).collect();
+    let mut manifest = Manifest {
+        version: 1,
+        created: super::now_rfc3339(),
+        since: since.map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)),
+        alg: TREE_ALG,
+        root: crate::merkle::root(TREE_ALG, &digests)?,
+        count: digests.len() as u64,
+        index: blake3_hex(&index),
+        node: did_key_from_public(kp.public.as_bytes()),
+        sig: String::new(),
+    };
+    let sig: Signature = kp.sign(signing_digest(&manifest)?.as_bytes());
+    manifest.sig = base64::engine::general_purpose::STANDARD.encode(sig.to_bytes());
+
+    let tmp = out.with_extension("tar.tmp");
+    let mut tar = tar::Builder::new(std::fs::File::create(&tmp)?);
+    append(&mut tar, MANIFEST, &serde_json::to_vec_pretty(&manifest)?)?;
+    append(&mut tar, INDEX, &index)?;
+    for (d, bytes) in digests.iter().zip(&objects) {
+        append(&mut tar, &format!("{OBJECTS}{d}.json"), bytes)?;
+    }
+    tar.into_inner()?.sync_all()?;
+    std::fs::rename(tmp, out)?;
+    Ok(manifest)
+}
+
+fn append(tar: &mut tar::Builder<std::fs::File>, name: &str, data: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(0);
+    header.set_cksum();
+    tar.append_data(&mut header, name, data)?;
+    Ok(())
+}
+
+/// Outcome of [`import`].
+#[derive(Debug)]
+pub struct ImportReport {
+    pub manifest: Manifest,
+    /// Digests that were new to this ledger.
+    pub added: Vec<String>,
+    /// Digests this ledger already held.
+    pub present: usize,
+}
+
+/// Verify the archive at `path` and add its objects to the local ledger.
+///
+/// The manifest must be signed by `signer` when it is set, and otherwise by a
+/// `full` peer in the registry ([`crate::sync::peers`]). Every object is
+/// classified locally and checked as a pulled object would be
+/// ([`crate::sync::pull::verify_object`]). Nothing is written unless every
+/// check passes.
+pub fn import(path: &Path, signer: Option<&str>) -> Result<ImportReport> {
+    let mut files: HashMap<String, Vec<u8>> = HashMap::new();
+    let mut tar = tar::Archive::new(std::fs::File::open(path)?);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().to_string();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        if files.insert(name.clone(), data).is_some() {
+            return Err(anyhow!("archive holds {name} twice"));
+        }
+    }
+
+    let manifest: Manifest = serde_json::from_slice(
+        files
+            .get(MANIFEST)
+            .ok_or_else(|| anyhow!("archive has no {MANIFEST}"))?,
+    )
+    .map_err(|e| anyhow!("bad {MANIFEST}: {e}"))?;
+    if manifest.version != 1 {
+        return Err(anyhow!("unsupported archive version {}", manifest.version));
+    }
+    manifest.verify()?;
+    match signer {
+        Some(want) if manifest.node != want => {
+            return Err(anyhow!(
+                "archive signed by {}, expected {want}",
+                manifest.node
+            ));
+        }
+        Some(_) => {}
+        None => {
+            let trust = crate::sync::peers::get(&manifest.node)?.map(|p| p.trust);
+            if trust != Some(TrustLevel::Full) {
+                return Err(anyhow!(
+                    "archive signed by {}, which is not a full peer; pass --signer to accept it",
+                    manifest.node
+                ));
+            }
+        }
+    }
+
+    let index = files
+        .get(INDEX)
+        .ok_or_else(|| anyhow!("archive has no {INDEX}"))?;
+    if blake3_hex(index) != manifest.index {
+        return Err(anyhow!("{INDEX} does not match the manifest"));
+    }
+    let mut entries = Vec::new();
+    for line in index.split(|b| *b == b'\n').filter(|l| !l.is_empty()) {
+        let e: Entry =
+            serde_json::from_slice(line).map_err(|e| anyhow!("bad {INDEX} line: {e}"))?;
+        entries.push(e);
+    }
+    let digests: Vec<String> = entries.iter().map(|e| e.digest.clone()).collect();
+    if digests.len() as u64 != manifest.count
+        || manifest.alg != TREE_ALG
+        || crate::merkle::root(TREE_ALG, &digests)? != manifest.root
+    {
+        return Err(anyhow!("index does not match the manifest root/count"));
+    }
+
+    let mut objects = Vec::with_capacity(entries.len());
+    for e in &entries {
+        let bytes = files
+            .get(&format!("{OBJECTS}{}.json", e.digest))
+            .ok_or_else(|| anyhow!("archive is missing object {}", e.digest))?;
+        if blake3_hex(bytes) != e.digest || crate::jcs::canonicalize(bytes)? != *bytes {
+            return Err(anyhow!("object {} does not match its digest", e.digest));
+        }
+        // Classify locally rather than trusting the exporter's kind.
+        let kind = super::classify(bytes);
+        crate::sync::pull::verify_object(&kind, bytes)
+            .map_err(|err| anyhow!("object {} rejected: {err}", e.digest))?;
+        objects.push((kind, bytes));
+    }
+
+    let known: HashSet<String> = super::list()?.into_iter().map(|e| e.digest).collect();
+    let mut report = ImportReport {
+        manifest,
+        added: Vec::new(),
+        present: 0,
+    };
+    for (e, (kind, bytes)) in entries.into_iter().zip(objects) {
+        if known.contains(&e.digest) {
+            report.present += 1;
+            continue;
+        }
+        super::add_json(&kind, bytes, e.commit, e.git_ref)?;
+        report.added.push(e.digest);
+    }
+    Ok(report)
+}
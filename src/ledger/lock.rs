@@ -0,0 +1,68 @@
+#![allow(clippy::missing_errors_doc)]
+//! Advisory lock serializing writers across processes that share a ledger dir.
+//!
+//! Index appends (which assign `seq`), `objects.log` appends, compaction and
+//! torn-tail repair, index rewrites, and peer registry and pull cursor edits
+//! all run under an exclusive lock on `ledger.lock` in the ledger dir, so a
+//! gateway and a CLI run against the same dir cannot interleave. Readers do not lock; they only consume
+//! complete records. The lock is re-entrant per thread: code holding it may
+//! call code that locks the same dir again.
+use anyhow::Result;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+pub const LOCK_FILE: &str = "ledger.lock";
+
+thread_local! {
+    /// Dirs this thread holds the lock on, with the locked file and depth.
+    static HELD: RefCell<HashMap<PathBuf, (File, usize)>> = RefCell::new(HashMap::new());
+}
+
+/// Exclusive hold on a ledger dir, released when the outermost guard drops.
+pub struct Held {
+    dir: PathBuf,
+    // Held per thread; must be dropped on the thread that took it.
+    _thread: PhantomData<*const ()>,
+}
+
+/// Block until this thread holds the lock on `dir`.
+pub fn exclusive(dir: &Path) -> Result<Held> {
+    let dir = dir.to_path_buf();
+    let nested = HELD.with(|h| {
+        h.borrow_mut()
+            .get_mut(&dir)
+            .map(|(_, depth)| *depth += 1)
+            .is_some()
+    });
+    if !nested {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(dir.join(LOCK_FILE))?;
+        file.lock()?;
+        HELD.with(|h| h.borrow_mut().insert(dir.clone(), (file, 1)));
+    }
+    Ok(Held {
+        dir,
+        _thread: PhantomData,
+    })
+}
+
+impl Drop for Held {
+    fn drop(&mut self) {
+        HELD.with(|h| {
+            let mut held = h.borrow_mut();
+            if let Some((_, depth)) = held.get_mut(&self.dir) {
+                *depth -= 1;
+                if *depth == 0 {
+                    // Closing the file releases the lock.
+                    held.remove(&self.dir);
+                }
+            }
+        });
+    }
+}
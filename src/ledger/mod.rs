@@ -0,0 +1,927 @@
+#![allow(clippy::missing_errors_doc)]
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+pub mod archive;
+pub mod lock;
+pub mod staging;
+pub mod store;
+
+use store::LedgerStore;
+
+/// One record of the append-only ledger index (`index.jsonl`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub seq: u64,       // position in ingest order, starting at 0
+    pub digest: String, // hex blake3 of the stored JSON
+    pub kind: String,   // "receipt" | "ops" | "provenance" | "unknown" | "tombstone"
+    pub ts: String,     // RFC3339 ingest time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+}
+
+/// Tree mode used for the ledger root served by the gateway.
+pub const TREE_ALG: crate::merkle::Alg = crate::merkle::Alg::Rfc6962;
+
+const INDEX_FILE: &str = "index.jsonl";
+
+/// `{ old, new }` lines recorded by [`migrate_canonical`] for re-keyed objects.
+const ALIAS_FILE: &str = "aliases.jsonl";
+
+/// Entry kind of ops (Terraform plan/apply) receipts; see [`crate::ops`].
+pub const OPS: &str = "ops";
+
+/// Kinds whose digests are leaves of the ledger tree.
+fn is_leaf_kind(kind: &str) -> bool {
+    kind == "receipt" || kind == OPS
+}
+
+/// Entry kind recording that [`gc`] collected `digest`. The receipt stays a
+/// leaf of the ledger tree, so roots and proofs issued earlier remain valid.
+pub const TOMBSTONE: &str = "tombstone";
+
+pub(crate) fn ledger_dir() -> Result<PathBuf> {
+    if let Ok(custom) = std::env::var("VAULTMESH_LEDGER_DIR") {
+        let dir = PathBuf::from(custom);
+        std::fs::create_dir_all(&dir)?;
+        return Ok(dir);
+    }
+    let home = dirs::home_dir().ok_or_else(|| anyhow!("no home dir"))?;
+    let dir = home.join(".vaultmesh").join("ledger");
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// In-memory view of `index.jsonl`, refreshed from the last read offset so
+/// that appends made by other processes are picked up without a rescan, and
+/// reloaded when another process replaces the file.
+/// Appends happen under the ledger dir lock ([`lock`]); the `INDEX` mutex
+/// only serializes threads of this process.
+struct Index {
+    dir: PathBuf,
+    store: Box<dyn LedgerStore>,
+    /// `index.jsonl` as opened; read from, and compared against the path to
+    /// notice a rewrite.
+    file: std::fs::File,
+    offset: u64,
+    entries: Vec<Entry>,
+    by_digest: HashMap<String, usize>,
+    receipts: Vec<String>,
+    frontier: crate::merkle::Frontier,
+    collected: HashSet<String>,
+    tombstones: Vec<Entry>,
+    /// Old digest -> digest it was re-keyed to by [`migrate_canonical`].
+    aliases: HashMap<String, String>,
+    /// Live entries sorted by digest; dropped whenever an entry is inserted.
+    sorted: Option<Arc<Vec<Entry>>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Alias {
+    old: String,
+    new: String,
+}
+
+static INDEX: Mutex<Option<Index>> = Mutex::new(None);
+
+impl Index {
+    fn open(dir: PathBuf) -> Result<Self> {
+        let store = store::open(&dir)?;
+        if !dir.join(INDEX_FILE).exists() {
+            let _held = lock::exclusive(&dir)?;
+            if !dir.join(INDEX_FILE).exists() {
+                bootstrap_index(&dir, store.as_ref())?;
+            }
+        }
+        let file = std::fs::File::open(dir.join(INDEX_FILE))?;
+        let mut idx = Self {
+            dir,
+            store,
+            file,
+            offset: 0,
+            entries: Vec::new(),
+            by_digest: HashMap::new(),
+            receipts: Vec::new(),
+            frontier: crate::merkle::Frontier::default(),
+            collected: HashSet::new(),
+            tombstones: Vec::new(),
+            aliases: HashMap::new(),
+            sorted: None,
+        };
+        idx.aliases = load_aliases(&idx.dir)?;
+        idx.refresh()?;
+        Ok(idx)
+    }
+
+    /// `digest`, or the digest [`migrate_canonical`] re-keyed it to.
+    fn resolve<'a>(&'a self, mut digest: &'a str) -> &'a str {
+        // Bounded, so a corrupt alias file cannot loop.
+        for _ in 0..=self.aliases.len() {
+            match self.aliases.get(digest) {
+                Some(new) => digest = new,
+                None => break,
+            }
+        }
+        digest
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(INDEX_FILE)
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        let len = self.file.metadata()?.len();
+        if len < self.offset || store::replaced(&self.path(), &self.file)? {
+            // Index was rewritten underneath us, possibly to the same length
+            // with different digests; start over.
+            *self = Self::open(self.dir.clone())?;
+            return Ok(());
+        }
+        if len == self.offset {
+            return Ok(());
+        }
+        self.file.seek(SeekFrom::Start(self.offset))?;
+        let mut buf = Vec::new();
+        self.file.read_to_end(&mut buf)?;
+        // Only consume complete lines; a concurrent writer may be mid-append.
+        let Some(end) = buf.iter().rposition(|b| *b == b'\n') else {
+            return Ok(());
+        };
+        let mut tombstoned = false;
+        for line in buf[..end].split(|b| *b == b'\n') {
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            let entry: Entry = serde_json::from_slice(line)
+                .map_err(|e| anyhow!("corrupt ledger index line: {e}"))?;
+            tombstoned |= entry.kind == TOMBSTONE;
+            self.insert(entry)?;
+        }
+        self.offset += end as u64 + 1;
+        if tombstoned {
+            // [`migrate_canonical`] records its aliases before tombstoning.
+            self.aliases = load_aliases(&self.dir)?;
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, entry: Entry) -> Result<()> {
+        self.sorted = None;
+        if entry.kind == TOMBSTONE {
+            self.collected.insert(entry.digest.clone());
+            self.tombstones.push(entry);
+            return Ok(());
+        }
+        // Re-ingesting a collected object makes it live again.
+        self.collected.remove(&entry.digest);
+        if is_leaf_kind(&entry.kind) {
+            self.frontier.push(&entry.digest)?;
+            self.receipts.push(entry.digest.clone());
+        }
+        self.by_digest
+            .insert(entry.digest.clone(), self.entries.len());
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Take the ledger dir lock and catch up on entries other processes
+    /// appended before it was free, so [`Self::next_seq`] and duplicate
+    /// checks made under it see every entry.
+    fn lock(&mut self) -> Result<lock::Held> {
+        let held = lock::exclusive(&self.dir)?;
+        self.refresh()?;
+        Ok(held)
+    }
+
+    /// Append `entry`, whose `seq` came from [`Self::next_seq`] under `_held`.
+    fn append(&mut self, _held: &lock::Held, entry: Entry) -> Result<()> {
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        let mut f = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path())?;
+        f.write_all(&line)?;
+        self.offset += line.len() as u64;
+        self.insert(entry)
+    }
+
+    fn next_seq(&self) -> u64 {
+        let last = [self.entries.last(), self.tombstones.last()];
+        last.into_iter()
+            .flatten()
+            .map(|e| e.seq + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Not collected, and the latest entry for its digest (a collected
+    /// object that was ingested again has an older, dead entry).
+    fn is_live(&self, e: &Entry) -> bool {
+        !self.collected.contains(&e.digest)
+            && self
+                .by_digest
+                .get(&e.digest)
+                .is_some_and(|&i| self.entries[i].seq == e.seq)
+    }
+}
+
+/// Run `f` against the index for the current ledger dir, reloading it when
+/// the dir changes and catching up on appends from other processes.
+fn with_index<T>(f: impl FnOnce(&mut Index) -> Result<T>) -> Result<T> {
+    let dir = ledger_dir()?;
+    let mut guard = INDEX
+        .lock()
+        .map_err(|_| anyhow!("ledger index lock poisoned"))?;
+    match guard.as_mut() {
+        Some(idx) if idx.dir == dir => idx.refresh()?,
+        _ => *guard = Some(Index::open(dir)?),
+    }
+    let idx = guard
+        .as_mut()
+        .ok_or_else(|| anyhow!("ledger index unavailable"))?;
+    let out = f(idx);
+    drop(guard);
+    out
+}
+
+fn load_aliases(dir: &Path) -> Result<HashMap<String, String>> {
+    let data = match std::fs::read(dir.join(ALIAS_FILE)) {
+        Ok(d) => d,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e.into()),
+    };
+    data.split(|b| *b == b'\n')
+        .filter(|l| !l.is_empty())
+        .map(|l| {
+            let a: Alias = serde_json::from_slice(l)
+                .map_err(|e| anyhow!("corrupt ledger alias line: {e}"))?;
+            Ok((a.old, a.new))
+        })
+        .collect()
+}
+
+/// Build `index.jsonl` for a ledger dir that predates the index by
+/// classifying every stored object once, in digest order.
+fn bootstrap_index(dir: &Path, store: &dyn LedgerStore) -> Result<()> {
+    let mut digests: Vec<String> = store.iter()?.collect::<Result<_>>()?;
+    digests.sort();
+    let ts = now_rfc3339();
+    let mut out = Vec::new();
+    for (seq, digest) in digests.into_iter().enumerate() {
+        let Some(bytes) = store.get(&digest)? else {
+            continue;
+        };
+        let entry = Entry {
+            seq: seq as u64,
+            digest,
+            kind: classify(&bytes),
+            ts: ts.clone(),
+            commit: None,
+            git_ref: None,
+        };
+        out.extend(serde_json::to_vec(&entry)?);
+        out.push(b'\n');
+    }
+    let tmp = dir.join(format!("{INDEX_FILE}.tmp"));
+    std::fs::write(&tmp, out)?;
+    std::fs::rename(tmp, dir.join(INDEX_FILE))?;
+    Ok(())
+}
+
+fn now_rfc3339() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+/// Canonicalize `bytes` (RFC 8785) and store them under their BLAKE3 digest.
+///
+/// An index entry is appended unless the digest is already known.
+/// `kind_hint` is recorded as the entry kind; pass `""` to classify by schema.
+pub fn add_json(
+    kind_hint: &str,
+    bytes: &[u8],
+    commit: Option<String>,
+    git_ref: Option<String>,
+) -> Result<String> {
+    let bytes = &crate::jcs::canonicalize(bytes)?;
+    let digest = crate::receipt::blake3_hex(bytes);
+    with_index(|idx| {
+        let held = idx.lock()?;
+        idx.store.put(&digest, bytes)?;
+        if idx.by_digest.contains_key(&digest) && !idx.collected.contains(&digest) {
+            return Ok(());
+        }
+        let kind = if kind_hint.is_empty() {
+            classify(bytes)
+        } else {
+            kind_hint.to_string()
+        };
+        let entry = Entry {
+            seq: idx.next_seq(),
+            digest: digest.clone(),
+            kind,
+            ts: now_rfc3339(),
+            commit,
+            git_ref,
+        };
+        idx.append(&held, entry)
+    })?;
+    Ok(digest)
+}
+
+/// Store a receipt, ops receipt or provenance document, classifying it by
+/// schema and recording the commit/ref it carries (`env.git_*` or `build.*`).
+pub fn add_document(bytes: &[u8]) -> Result<String> {
+    let v: serde_json::Value =
+        serde_json::from_slice(bytes).map_err(|e| anyhow!("not JSON: {e}"))?;
+    let kind = classify_value(&v);
+    if kind == OPS {
+        return add_ops(&serde_json::from_value(v)?);
+    }
+    if kind == "unknown" {
+        return Err(anyhow!("not a valid receipt/provenance"));
+    }
+    let (commit, git_ref) = document_refs(kind, &v);
+    add_json(kind, bytes, commit, git_ref)
+}
+
+/// Store `bytes` as they are under `kind`, as [`classify`] found it, with
+/// the commit and ref the document itself names.
+///
+/// Used for objects from peers and archives, so neither the kind nor the
+/// commit/ref they list for an object is recorded.
+pub(crate) fn add_classified(kind: &str, bytes: &[u8]) -> Result<String> {
+    let v: serde_json::Value =
+        serde_json::from_slice(bytes).map_err(|e| anyhow!("not JSON: {e}"))?;
+    let (commit, git_ref) = document_refs(kind, &v);
+    add_json(kind, bytes, commit, git_ref)
+}
+
+/// `commit` and `git_ref` a document of `kind` records about its build.
+fn document_refs(kind: &str, v: &serde_json::Value) -> (Option<String>, Option<String>) {
+    let (commit, git_ref) = match kind {
+        "receipt" => (v.pointer("/env/git_commit"), v.pointer("/env/git_ref")),
+        "provenance" => (v.pointer("/build/commit"), v.pointer("/build/ref")),
+        // As [`add_ops`] records it.
+        OPS => (v.pointer("/build/commit").filter(|c| *c != ""), None),
+        _ => (None, None),
+    };
+    let text = |x: Option<&serde_json::Value>| x.and_then(|s| s.as_str()).map(str::to_string);
+    (text(commit), text(git_ref))
+}
+
+/// Store an ops receipt under its typed serialization and record its
+/// `build.commit`.
+///
+/// The same receipt gets the same digest however its JSON was laid out.
+/// Callers that accept receipts from peers should run
+/// [`crate::ops::Receipt::verify_for_ingest`] first.
+///
+/// Like every other ledger object, the receipt's tree leaf is this storage
+/// digest ([`ops_digest`]), not its signed `leaf`: `/v1/proof/:digest` and
+/// `/v1/sth` prove the stored document, and `vaultmesh verify --proof` folds
+/// [`ops_digest`] of the receipt through that proof.
+pub fn add_ops(rec: &crate::ops::Receipt) -> Result<String> {
+    let bytes = serde_json::to_vec(rec)?;
+    let commit = Some(rec.build.commit.clone()).filter(|c| !c.is_empty());
+    add_json(OPS, &bytes, commit, None)
+}
+
+/// Digest [`add_ops`] stores `rec` under: BLAKE3 of the RFC 8785 form of its
+/// typed serialization.
+pub fn ops_digest(rec: &crate::ops::Receipt) -> Result<String> {
+    let bytes = crate::jcs::canonicalize(&serde_json::to_vec(rec)?)?;
+    Ok(crate::receipt::blake3_hex(&bytes))
+}
+
+/// Stored bytes for `digest`, rejected if they no longer hash to it.
+pub fn get_json(digest: &str) -> Result<Vec<u8>> {
+    let data = with_index(|idx| idx.store.get(digest))?
+        .ok_or_else(|| anyhow!("no ledger object {digest}"))?;
+    let actual = crate::receipt::blake3_hex(&data);
+    if actual != digest {
+        return Err(anyhow!(
+            "ledger object {digest} is corrupt (content hashes to {actual})"
+        ));
+    }
+    Ok(data)
+}
+
+/// Outcome of [`migrate_canonical`].
+#[derive(Debug, Default)]
+pub struct MigrateReport {
+    /// `(old, new)` digests of objects that were re-keyed.
+    pub rekeyed: Vec<(String, String)>,
+    pub unchanged: usize,
+    /// Digests whose objects are missing or not JSON; left as they were.
+    pub skipped: Vec<String>,
+    /// Ledger tree root and size before and after the migration.
+    pub old_root: (String, usize),
+    pub new_root: (String, usize),
+}
+
+/// Re-key every live object under the digest of its canonical (JCS) form.
+///
+/// The index stays append-only: a re-keyed object's old entry is tombstoned
+/// in place, as [`gc`] does, and the canonical object is appended under its
+/// new digest, unless that digest is already live. Old receipt leaves stay in
+/// the ledger tree, so the new tree extends the old one: tree heads and
+/// proofs served earlier stay valid and peers' pull cursors keep their place.
+///
+/// Signed receipts keep naming the old digest in `provenance_ref`, so every
+/// re-key is also recorded in `aliases.jsonl`; [`fsck`] and [`gc`] resolve
+/// references through it.
+pub fn migrate_canonical() -> Result<MigrateReport> {
+    with_index(|idx| {
+        let held = idx.lock()?;
+        let mut report = MigrateReport {
+            old_root: (idx.frontier.root(), idx.receipts.len()),
+            ..MigrateReport::default()
+        };
+        let live: Vec<Entry> = idx
+            .entries
+            .iter()
+            .filter(|e| idx.is_live(e))
+            .cloned()
+            .collect();
+        let mut moved = Vec::new();
+        for entry in live {
+            let canon = idx
+                .store
+                .get(&entry.digest)
+                .ok()
+                .flatten()
+                .and_then(|b| crate::jcs::canonicalize(&b).ok());
+            let Some(canon) = canon else {
+                report.skipped.push(entry.digest);
+                continue;
+            };
+            let digest = crate::receipt::blake3_hex(&canon);
+            if digest == entry.digest {
+                report.unchanged += 1;
+                continue;
+            }
+            idx.store.put(&digest, &canon)?;
+            report.rekeyed.push((entry.digest.clone(), digest.clone()));
+            moved.push((entry, digest));
+        }
+        if moved.is_empty() {
+            report.new_root.clone_from(&report.old_root);
+            return Ok(report);
+        }
+
+        // Aliases first, so a reader never sees an old digest collected
+        // without the digest it moved to.
+        let mut lines = Vec::new();
+        for (old, new) in &report.rekeyed {
+            let alias = Alias {
+                old: old.clone(),
+                new: new.clone(),
+            };
+            lines.extend(serde_json::to_vec(&alias)?);
+            lines.push(b'\n');
+        }
+        let mut aliases = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(idx.dir.join(ALIAS_FILE))?;
+        aliases.write_all(&lines)?;
+        aliases.sync_data()?;
+        idx.aliases = load_aliases(&idx.dir)?;
+
+        let ts = now_rfc3339();
+        let gone: HashSet<String> = moved.iter().map(|(e, _)| e.digest.clone()).collect();
+        for (old, digest) in moved {
+            let tombstone = Entry {
+                seq: idx.next_seq(),
+                digest: old.digest.clone(),
+                kind: TOMBSTONE.to_string(),
+                ts: ts.clone(),
+                commit: None,
+                git_ref: None,
+            };
+            idx.append(&held, tombstone)?;
+            if idx.by_digest.contains_key(&digest) && !idx.collected.contains(&digest) {
+                continue;
+            }
+            let entry = Entry {
+                seq: idx.next_seq(),
+                digest,
+                ..old
+            };
+            idx.append(&held, entry)?;
+        }
+        idx.store.retain(&mut |d| !gone.contains(d))?;
+        idx.store.compact()?;
+        report.new_root = (idx.frontier.root(), idx.receipts.len());
+        Ok(report)
+    })
+}
+
+/// What [`gc`] may collect. An object is collected only if it matches every
+/// criterion that is set; at least one of `older_than` or `kinds` is required.
+#[derive(Debug, Clone, Default)]
+pub struct Retention {
+    /// Collect objects ingested before this instant.
+    pub older_than: Option<chrono::DateTime<chrono::Utc>>,
+    /// Collect only these kinds; any kind when empty.
+    pub kinds: Vec<String>,
+    /// Keep every receipt covered by these ledger tree heads.
+    pub sealed: Vec<crate::sth::SignedTreeHead>,
+}
+
+/// Outcome of [`gc`].
+#[derive(Debug, Default)]
+pub struct GcReport {
+    /// Entries collected (or, for a dry run, that would be).
+    pub collected: Vec<Entry>,
+    /// Candidates kept because a sealed tree head covers them.
+    pub kept_sealed: usize,
+    /// Candidates kept because a live receipt's `provenance_ref` names them.
+    pub kept_referenced: usize,
+}
+
+/// Delete stored objects selected by `policy`.
+///
+/// Receipts covered by a sealed tree head are kept, as is any provenance referenced by a receipt that
+/// stays live. Each collected object gets a tombstone in the index before its
+/// bytes are removed; the ledger tree is unchanged. With `dry_run` nothing is
+/// written.
+pub fn gc(policy: &Retention, dry_run: bool) -> Result<GcReport> {
+    if policy.older_than.is_none() && policy.kinds.is_empty() {
+        return Err(anyhow!("retention policy needs an age or a kind"));
+    }
+    with_index(|idx| {
+        // Candidates are chosen under the dir lock, so an object that another
+        // process re-ingests or references meanwhile is not collected.
+        let held = idx.lock()?;
+        let mut sealed = HashSet::new();
+        for head in &policy.sealed {
+            sealed.extend(covered_receipts(idx, head)?);
+        }
+        let mut report = GcReport::default();
+        let mut doomed: Vec<&Entry> = Vec::new();
+        let mut live_receipts = Vec::new();
+        for e in idx.entries.iter().filter(|e| idx.is_live(e)) {
+            let candidate = policy.kinds.is_empty() || policy.kinds.contains(&e.kind);
+            let candidate = candidate
+                && policy.older_than.is_none_or(|cutoff| {
+                    chrono::DateTime::parse_from_rfc3339(&e.ts).is_ok_and(|ts| ts < cutoff)
+                });
+            let is_sealed = sealed.contains(&e.digest);
+            if candidate && !is_sealed {
+                doomed.push(e);
+                continue;
+            }
+            report.kept_sealed += usize::from(candidate);
+            if e.kind == "receipt" {
+                live_receipts.push(e.digest.as_str());
+            }
+        }
+        let mut referenced = HashSet::new();
+        for digest in live_receipts {
+            let Some(bytes) = idx.store.get(digest)? else {
+                continue;
+            };
+            let v: serde_json::Value = serde_json::from_slice(&bytes).unwrap_or_default();
+            if let Some(d) = v.pointer("/provenance_ref/digest").and_then(|d| d.as_str()) {
+                referenced.insert(idx.resolve(d).to_string());
+            }
+        }
+        doomed.retain(|e| {
+            let keep = referenced.contains(&e.digest);
+            report.kept_referenced += usize::from(keep);
+            !keep
+        });
+        report.collected = doomed.into_iter().cloned().collect();
+        if dry_run || report.collected.is_empty() {
+            return Ok(report);
+        }
+        let ts = now_rfc3339();
+        let gone: HashSet<String> = report.collected.iter().map(|e| e.digest.clone()).collect();
+        for e in &report.collected {
+            let tombstone = Entry {
+                seq: idx.next_seq(),
+                digest: e.digest.clone(),
+                kind: TOMBSTONE.to_string(),
+                ts: ts.clone(),
+                commit: None,
+                git_ref: None,
+            };
+            idx.append(&held, tombstone)?;
+        }
+        idx.store.retain(&mut |d| !gone.contains(d))?;
+        idx.store.compact()?;
+        Ok(report)
+    })
+}
+
+/// Receipt digests covered by `head`, which must be a valid tree head over a
+/// prefix of this ledger.
+fn covered_receipts<'a>(idx: &'a Index, head: &crate::sth::SignedTreeHead) -> Result<&'a [String]> {
+    head.verify()?;
+    let size = usize::try_from(head.size)?;
+    let prefix = idx
+        .receipts
+        .get(..size)
+        .filter(|_| head.alg == TREE_ALG)
+        .ok_or_else(|| anyhow!("tree head of size {size} is not over this ledger"))?;
+    if crate::merkle::root(TREE_ALG, prefix)? != head.root {
+        return Err(anyhow!("tree head root does not match this ledger"));
+    }
+    Ok(prefix)
+}
+
+/// A single finding from [`fsck`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// Stored bytes hash to `actual`, not to the digest they are stored under.
+    BadDigest { actual: String },
+    /// Indexed, but the object is gone from the store.
+    Missing,
+    /// Object is not JSON.
+    NotJson(String),
+    /// Indexed as a receipt/provenance but fails that schema.
+    Schema(String),
+    /// Receipt signature missing or invalid; for ops receipts, also a
+    /// non-canonical leaf or too few approvals.
+    Signature(String),
+    /// `provenance_ref.digest` does not resolve to a stored object.
+    DanglingRef(String),
+    /// Stored but not indexed, or indexed and already collected.
+    Orphan,
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadDigest { actual } => write!(f, "bad-digest  content hashes to {actual}"),
+            Self::Missing => f.write_str("missing  indexed object not in store"),
+            Self::NotJson(e) => write!(f, "not-json  {e}"),
+            Self::Schema(e) => write!(f, "schema  {e}"),
+            Self::Signature(e) => write!(f, "signature  {e}"),
+            Self::DanglingRef(d) => write!(f, "dangling-ref  provenance_ref {d} not stored"),
+            Self::Orphan => f.write_str("orphan  stored object not in index"),
+        }
+    }
+}
+
+/// Outcome of [`fsck`].
+#[derive(Debug, Default)]
+pub struct FsckReport {
+    /// Objects read from the store.
+    pub checked: usize,
+    /// `(digest, problem)` pairs; empty for a healthy ledger.
+    pub problems: Vec<(String, Problem)>,
+}
+
+impl FsckReport {
+    #[must_use]
+    pub const fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Whether `digest` was ever indexed, including objects [`gc`] collected.
+pub fn contains(digest: &str) -> Result<bool> {
+    with_index(|idx| Ok(idx.by_digest.contains_key(digest)))
+}
+
+/// Audit the ledger. Read-only.
+///
+/// Rehashes every stored object against its digest, re-runs the schema and
+/// signature checks for indexed receipts and provenance, resolves every
+/// `provenance_ref`, and reports objects missing from either the store or
+/// the index.
+pub fn fsck() -> Result<FsckReport> {
+    with_index(|idx| {
+        let mut report = FsckReport::default();
+        let mut stored: Vec<String> = idx.store.iter()?.collect::<Result<_>>()?;
+        stored.sort();
+        for digest in &stored {
+            if !idx.by_digest.contains_key(digest) || idx.collected.contains(digest) {
+                report.problems.push((digest.clone(), Problem::Orphan));
+            }
+        }
+        for entry in idx.entries.iter().filter(|e| idx.is_live(e)) {
+            let Some(bytes) = idx.store.get(&entry.digest)? else {
+                report
+                    .problems
+                    .push((entry.digest.clone(), Problem::Missing));
+                continue;
+            };
+            report.checked += 1;
+            for problem in check_object(idx, entry, &bytes) {
+                report.problems.push((entry.digest.clone(), problem));
+            }
+        }
+        Ok(report)
+    })
+}
+
+fn check_object(idx: &Index, entry: &Entry, bytes: &[u8]) -> Vec<Problem> {
+    let mut out = Vec::new();
+    let actual = crate::receipt::blake3_hex(bytes);
+    if actual != entry.digest {
+        out.push(Problem::BadDigest { actual });
+    }
+    let v: serde_json::Value = match serde_json::from_slice(bytes) {
+        Ok(v) => v,
+        Err(e) => {
+            out.push(Problem::NotJson(e.to_string()));
+            return out;
+        }
+    };
+    match entry.kind.as_str() {
+        "receipt" => {
+            if let Err(e) = crate::schema::validate_receipt(&v) {
+                out.push(Problem::Schema(e.to_string()));
+                return out;
+            }
+            let sig = serde_json::from_value::<crate::receipt::Receipt>(v.clone())
+                .map_err(anyhow::Error::from)
+                .and_then(|r| crate::receipt::verify_receipt(&r));
+            if let Err(e) = sig {
+                out.push(Problem::Signature(e.to_string()));
+            }
+            if let Some(d) = v.pointer("/provenance_ref/digest").and_then(|d| d.as_str()) {
+                if !idx.store.has(idx.resolve(d)).unwrap_or(false) {
+                    out.push(Problem::DanglingRef(d.to_string()));
+                }
+            }
+        }
+        OPS => match crate::schema::validate_ops_receipt(&v)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| Ok(serde_json::from_value::<crate::ops::Receipt>(v)?))
+        {
+            Ok(rec) => {
+                if let Err(e) = rec.verify_for_ingest() {
+                    out.push(Problem::Signature(e.to_string()));
+                }
+            }
+            Err(e) => out.push(Problem::Schema(e.to_string())),
+        },
+        "provenance" => {
+            if let Err(e) = crate::schema::validate_provenance(&v) {
+                out.push(Problem::Schema(e.to_string()));
+            }
+        }
+        _ => {}
+    }
+    out
+}
+
+/// Index entries in ingest order, leaving out collected objects.
+pub fn list() -> Result<Vec<Entry>> {
+    with_index(|idx| {
+        Ok(idx
+            .entries
+            .iter()
+            .filter(|e| idx.is_live(e))
+            .cloned()
+            .collect())
+    })
+}
+
+/// Live entries sorted by digest, as [`crate::sync::reconcile`] expects. The
+/// list is built once and shared until the index changes, so repeated range
+/// queries neither copy nor re-sort the index.
+pub fn sorted_by_digest() -> Result<Arc<Vec<Entry>>> {
+    with_index(|idx| {
+        if let Some(sorted) = &idx.sorted {
+            return Ok(Arc::clone(sorted));
+        }
+        let mut live: Vec<Entry> = idx
+            .entries
+            .iter()
+            .filter(|e| idx.is_live(e))
+            .cloned()
+            .collect();
+        live.sort_by(|a, b| a.digest.cmp(&b.digest));
+        let sorted = Arc::new(live);
+        idx.sorted = Some(Arc::clone(&sorted));
+        Ok(sorted)
+    })
+}
+
+/// Index query over live entries; unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub kind: Option<String>,
+    /// Commit SHA or unambiguous prefix of one.
+    pub commit: Option<String>,
+    /// Exact ref, e.g. `refs/heads/main`.
+    pub git_ref: Option<String>,
+}
+
+impl Filter {
+    #[must_use]
+    pub fn matches(&self, e: &Entry) -> bool {
+        self.kind.as_ref().is_none_or(|k| *k == e.kind)
+            && self.commit.as_ref().is_none_or(|c| {
+                e.commit
+                    .as_ref()
+                    .is_some_and(|ec| ec.starts_with(c.as_str()))
+            })
+            && self
+                .git_ref
+                .as_ref()
+                .is_none_or(|r| e.git_ref.as_ref() == Some(r))
+    }
+}
+
+/// Index entries matching `filter`, in ingest order.
+pub fn query(filter: &Filter) -> Result<Vec<Entry>> {
+    with_index(|idx| {
+        Ok(idx
+            .entries
+            .iter()
+            .filter(|e| idx.is_live(e) && filter.matches(e))
+            .cloned()
+            .collect())
+    })
+}
+
+/// Receipt and ops receipt digests in tree (ingest) order: the leaves of the
+/// ledger Merkle tree.
+pub fn receipt_leaves() -> Result<Vec<String>> {
+    with_index(|idx| Ok(idx.receipts.clone()))
+}
+
+/// Audit path for a stored receipt against the current ledger root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub digest: String,
+    pub alg: crate::merkle::Alg,
+    pub root: String,
+    pub size: usize,
+    pub index: usize,
+    pub path: Vec<crate::merkle::PathStep>,
+}
+
+impl InclusionProof {
+    /// True when `digest` folds through `path` to `root`.
+    pub fn verify(&self) -> Result<bool> {
+        crate::merkle::verify_path(self.alg, &self.digest, &self.path, &self.root)
+    }
+}
+
+/// Inclusion proof for `digest`, or `None` if it is not a stored receipt.
+/// `digest` is the ledger (storage) digest; for ops receipts that is
+/// [`ops_digest`], not the receipt's signed `leaf`.
+pub fn inclusion_proof(digest: &str) -> Result<Option<InclusionProof>> {
+    let leaves = receipt_leaves()?;
+    let Some(index) = leaves.iter().position(|l| l == digest) else {
+        return Ok(None);
+    };
+    let path = crate::merkle::audit_path(TREE_ALG, &leaves, index)?
+        .ok_or_else(|| anyhow!("leaf index out of range"))?;
+    Ok(Some(InclusionProof {
+        digest: digest.to_string(),
+        alg: TREE_ALG,
+        root: crate::merkle::root(TREE_ALG, &leaves)?,
+        size: leaves.len(),
+        index,
+        path,
+    }))
+}
+
+/// RFC 6962 consistency proof that the ledger tree at `old_size` leaves is a
+/// prefix of the tree at `new_size` leaves.
+pub fn consistency_proof(old_size: usize, new_size: usize) -> Result<Vec<String>> {
+    let leaves = receipt_leaves()?;
+    let prefix = leaves
+        .get(..new_size)
+        .ok_or_else(|| anyhow!("new size {new_size} exceeds tree size {}", leaves.len()))?;
+    crate::merkle::consistency_proof(prefix, old_size)
+}
+
+/// Current ledger root and tree size.
+pub fn merkle_root() -> Result<(String, usize)> {
+    with_index(|idx| Ok((idx.frontier.root(), idx.receipts.len())))
+}
+
+pub(crate) fn classify(bytes: &[u8]) -> String {
+    serde_json::from_slice::<serde_json::Value>(bytes)
+        .map_or("unknown", |v| classify_value(&v))
+        .into()
+}
+
+fn classify_value(v: &serde_json::Value) -> &'static str {
+    if crate::schema::validate_receipt(v).is_ok() {
+        return "receipt";
+    }
+    if crate::schema::validate_provenance(v).is_ok() {
+        return "provenance";
+    }
+    if crate::schema::validate_ops_receipt(v).is_ok() {
+        return OPS;
+    }
+    "unknown"
+}
@@ -0,0 +1,86 @@
+#![allow(clippy::missing_errors_doc)]
+//! Staging area for objects pushed by quarantined peers.
+//!
+//! Staged objects live under `staging/` in the ledger dir, outside the object
+//! store and index, so they never become leaves of the ledger tree:
+//! - `staging/<digest>.json`: the canonical object
+//! - `staging/index.jsonl`: one [`Staged`] line per push
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const DIR: &str = "staging";
+const INDEX: &str = "index.jsonl";
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// One object a quarantined peer pushed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Staged {
+    pub digest: String,
+    /// Kind the pushing endpoint accepted it as (`receipt`, `provenance`, `ops`).
+    pub kind: String,
+    /// Registry id of the pushing peer.
+    pub peer: String,
+    pub ts: String,
+}
+
+fn staging_dir() -> Result<PathBuf> {
+    let dir = super::ledger_dir()?.join(DIR);
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Canonicalize `bytes` and keep them in the staging area on behalf of `peer`.
+pub fn stage(kind: &str, bytes: &[u8], peer: &str) -> Result<String> {
+    let bytes = crate::jcs::canonicalize(bytes)?;
+    let digest = crate::receipt::blake3_hex(&bytes);
+    let dir = staging_dir()?;
+    let _guard = LOCK
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let path = dir.join(format!("{digest}.json"));
+    if !path.exists() {
+        let tmp = dir.join(format!("{digest}.json.tmp"));
+        std::fs::write(&tmp, &bytes)?;
+        std::fs::rename(tmp, path)?;
+    }
+    let staged = Staged {
+        digest: digest.clone(),
+        kind: kind.to_string(),
+        peer: peer.to_string(),
+        ts: super::now_rfc3339(),
+    };
+    let mut f = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(INDEX))?;
+    let mut line = serde_json::to_vec(&staged)?;
+    line.push(b'\n');
+    f.write_all(&line)?;
+    Ok(digest)
+}
+
+/// Every staged push, oldest first.
+pub fn list() -> Result<Vec<Staged>> {
+    let path = staging_dir()?.join(INDEX);
+    let Ok(data) = std::fs::read(&path) else {
+        return Ok(Vec::new());
+    };
+    data.split(|b| *b == b'\n')
+        .filter(|l| !l.is_empty())
+        .map(|l| Ok(serde_json::from_slice(l)?))
+        .collect()
+}
+
+/// Staged bytes for `digest`, if any.
+pub fn get(digest: &str) -> Result<Option<Vec<u8>>> {
+    super::store::check_digest(digest)?;
+    match std::fs::read(staging_dir()?.join(format!("{digest}.json"))) {
+        Ok(b) => Ok(Some(b)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
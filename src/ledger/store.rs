@@ -0,0 +1,450 @@
+#![allow(clippy::missing_errors_doc)]
+//! Object storage behind the ledger index.
+//!
+//! `fs` keeps one file per object in a sharded `ab/cdef….json` layout; `log`
+//! appends every object to a single `objects.log`, which keeps directory size
+//! constant for nodes holding millions of receipts.
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Content-addressed object store keyed by hex digest.
+pub trait LedgerStore: Send + Sync {
+    /// Store `bytes` under `digest`; storing an existing digest is a no-op.
+    fn put(&self, digest: &str, bytes: &[u8]) -> Result<()>;
+    fn get(&self, digest: &str) -> Result<Option<Vec<u8>>>;
+    fn has(&self, digest: &str) -> Result<bool>;
+    /// Digests of every stored object, in no particular order.
+    #[allow(clippy::iter_not_returning_iterator)] // listing can fail up front
+    fn iter(&self) -> Result<Box<dyn Iterator<Item = Result<String>> + '_>>;
+    /// Remove `digest`; returns whether it was present.
+    fn delete(&self, digest: &str) -> Result<bool>;
+
+    /// Delete every object `keep` rejects and return the deleted digests.
+    fn retain(&self, keep: &mut dyn FnMut(&str) -> bool) -> Result<Vec<String>> {
+        let doomed: Vec<String> = self
+            .iter()?
+            .filter(|d| d.as_ref().map_or(true, |d| !keep(d)))
+            .collect::<Result<_>>()?;
+        for d in &doomed {
+            self.delete(d)?;
+        }
+        Ok(doomed)
+    }
+
+    /// Reclaim space left by deletes; a no-op for stores that free it
+    /// immediately.
+    fn compact(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Fs,
+    Log,
+}
+
+impl std::str::FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "fs" => Ok(Self::Fs),
+            "log" => Ok(Self::Log),
+            other => Err(anyhow!("unknown ledger backend: {other} (expected fs|log)")),
+        }
+    }
+}
+
+/// Backend for `dir`: `VAULTMESH_LEDGER_BACKEND` if set, otherwise `log` when
+/// the dir already holds an `objects.log`, otherwise `fs`.
+pub fn backend_for(dir: &Path) -> Result<Backend> {
+    match std::env::var("VAULTMESH_LEDGER_BACKEND") {
+        Ok(b) if !b.trim().is_empty() => b.trim().parse(),
+        _ if dir.join(LOG_FILE).exists() => Ok(Backend::Log),
+        _ => Ok(Backend::Fs),
+    }
+}
+
+pub fn open(dir: &Path) -> Result<Box<dyn LedgerStore>> {
+    Ok(match backend_for(dir)? {
+        Backend::Fs => Box::new(FsStore::new(dir)),
+        Backend::Log => Box::new(LogStore::open(dir)?),
+    })
+}
+
+// ---------- fs ----------
+
+/// One file per object, sharded git-style as `ab/cdef….json` under the
+/// ledger dir. Objects written by older nodes as flat `<digest>.json` files
+/// stay readable in place.
+pub struct FsStore {
+    dir: PathBuf,
+}
+
+impl FsStore {
+    #[must_use]
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    fn sharded(&self, digest: &str) -> Result<PathBuf> {
+        check_digest(digest)?;
+        let (shard, rest) = digest.split_at(2);
+        Ok(self.dir.join(shard).join(format!("{rest}.json")))
+    }
+
+    fn flat(&self, digest: &str) -> PathBuf {
+        self.dir.join(format!("{digest}.json"))
+    }
+
+    /// Existing path for `digest`, preferring the sharded layout.
+    fn locate(&self, digest: &str) -> Result<Option<PathBuf>> {
+        let sharded = self.sharded(digest)?;
+        if sharded.exists() {
+            return Ok(Some(sharded));
+        }
+        let flat = self.flat(digest);
+        Ok(flat.exists().then_some(flat))
+    }
+}
+
+/// Digests name files, so only accept hex long enough to shard.
+pub(super) fn check_digest(digest: &str) -> Result<()> {
+    if digest.len() > 2 && digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(anyhow!("invalid ledger digest: {digest:?}"))
+    }
+}
+
+impl LedgerStore for FsStore {
+    /// Written to a temp file in the shard dir, fsynced, then renamed into
+    /// place, so a crash never leaves a partial object under its digest. An
+    /// existing file that does not hash to `digest` (corrupted on disk) is
+    /// replaced the same way rather than kept.
+    fn put(&self, digest: &str, bytes: &[u8]) -> Result<()> {
+        let existing = self.locate(digest)?;
+        if let Some(old) = &existing {
+            if crate::receipt::blake3_hex(&std::fs::read(old)?) == digest {
+                return Ok(());
+            }
+        }
+        let path = self.sharded(digest)?;
+        let shard = path.parent().ok_or_else(|| anyhow!("no shard dir"))?;
+        std::fs::create_dir_all(shard)?;
+        let tmp = shard.join(format!(".{digest}.{}.tmp", std::process::id()));
+        let mut f = File::create(&tmp)?;
+        f.write_all(bytes)?;
+        f.sync_all()?;
+        drop(f);
+        std::fs::rename(&tmp, &path)?;
+        // Persist the rename itself; not supported on every platform.
+        let _ = File::open(shard).and_then(|d| d.sync_all());
+        // A corrupt flat copy would outlive the repair in `iter`.
+        if let Some(old) = existing.filter(|old| *old != path) {
+            std::fs::remove_file(old)?;
+        }
+        Ok(())
+    }
+
+    fn get(&self, digest: &str) -> Result<Option<Vec<u8>>> {
+        let Some(path) = self.locate(digest)? else {
+            return Ok(None);
+        };
+        Ok(Some(std::fs::read(path)?))
+    }
+
+    fn has(&self, digest: &str) -> Result<bool> {
+        Ok(self.locate(digest)?.is_some())
+    }
+
+    fn iter(&self) -> Result<Box<dyn Iterator<Item = Result<String>> + '_>> {
+        let mut out = Vec::new();
+        for ent in std::fs::read_dir(&self.dir)? {
+            let ent = ent?;
+            let name = ent.file_name().to_string_lossy().to_string();
+            if let Some(d) = name.strip_suffix(".json") {
+                out.push(d.to_string());
+                continue;
+            }
+            let is_shard = name.len() == 2 && name.bytes().all(|b| b.is_ascii_hexdigit());
+            if !is_shard || !ent.file_type()?.is_dir() {
+                continue;
+            }
+            for obj in std::fs::read_dir(ent.path())? {
+                let obj = obj?.file_name().to_string_lossy().to_string();
+                if let Some(rest) = obj.strip_suffix(".json") {
+                    out.push(format!("{name}{rest}"));
+                }
+            }
+        }
+        Ok(Box::new(out.into_iter().map(Ok)))
+    }
+
+    fn delete(&self, digest: &str) -> Result<bool> {
+        let mut removed = false;
+        for path in [self.sharded(digest)?, self.flat(digest)] {
+            match std::fs::remove_file(path) {
+                Ok(()) => removed = true,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(removed)
+    }
+}
+
+// ---------- log ----------
+
+const LOG_FILE: &str = "objects.log";
+const OP_PUT: u8 = 1;
+const OP_DELETE: u8 = 2;
+
+/// Single-file log-structured store.
+///
+/// Records are `[op:u8][digest_len:u8][digest][data_len:u32 LE][data]`; a
+/// delete is a tombstone record with no data. The live set is rebuilt by
+/// scanning on open and caught up from the last scanned offset when another
+/// process has appended. A torn trailing record (crash mid-append) is cut off
+/// on open. `compact` rewrites the file without dead records and renames it
+/// into place; other processes notice the new file and rescan it.
+///
+/// Appends, compaction and tail repair hold the ledger dir lock
+/// ([`super::lock`]), so a trailing partial record seen under the lock is
+/// never another process's append in flight.
+pub struct LogStore {
+    dir: PathBuf,
+    inner: Mutex<LogInner>,
+}
+
+struct LogInner {
+    path: PathBuf,
+    file: File,
+    scanned: u64,
+    // digest -> (data offset, data len)
+    live: HashMap<String, (u64, u32)>,
+}
+
+impl LogStore {
+    pub fn open(dir: &Path) -> Result<Self> {
+        let path = dir.join(LOG_FILE);
+        let _held = super::lock::exclusive(dir)?;
+        let mut inner = LogInner {
+            file: open_log(&path)?,
+            path,
+            scanned: 0,
+            live: HashMap::new(),
+        };
+        let complete = inner.catch_up()?;
+        if complete < inner.file.metadata()?.len() {
+            inner.file.set_len(complete)?;
+        }
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            inner: Mutex::new(inner),
+        })
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, LogInner>> {
+        self.inner
+            .lock()
+            .map_err(|_| anyhow!("ledger log lock poisoned"))
+    }
+}
+
+fn open_log(path: &Path) -> Result<File> {
+    Ok(OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)?)
+}
+
+/// Whether `path` no longer names the file `file` has open, because another
+/// process compacted the log (or rewrote the index).
+#[cfg(unix)]
+pub(super) fn replaced(path: &Path, file: &File) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let (named, open) = (std::fs::metadata(path)?, file.metadata()?);
+    Ok((named.dev(), named.ino()) != (open.dev(), open.ino()))
+}
+
+#[cfg(not(unix))]
+pub(super) fn replaced(_path: &Path, _file: &File) -> Result<bool> {
+    Ok(false)
+}
+
+const fn header_len(digest: &str) -> u64 {
+    2 + digest.len() as u64 + 4
+}
+
+fn encode(op: u8, digest: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let dlen = u8::try_from(digest.len()).map_err(|_| anyhow!("digest too long"))?;
+    let len = u32::try_from(data.len()).map_err(|_| anyhow!("object too large"))?;
+    let mut rec = Vec::with_capacity(6 + digest.len() + data.len());
+    rec.push(op);
+    rec.push(dlen);
+    rec.extend_from_slice(digest.as_bytes());
+    rec.extend_from_slice(&len.to_le_bytes());
+    rec.extend_from_slice(data);
+    Ok(rec)
+}
+
+impl LogInner {
+    /// Scan records appended since the last scan; returns the offset just past
+    /// the last complete record.
+    fn catch_up(&mut self) -> Result<u64> {
+        if replaced(&self.path, &self.file)? {
+            self.file = open_log(&self.path)?;
+            self.scanned = 0;
+            self.live.clear();
+        }
+        let end = self.file.metadata()?.len();
+        if end <= self.scanned {
+            return Ok(self.scanned);
+        }
+        self.file.seek(SeekFrom::Start(self.scanned))?;
+        let mut r = BufReader::new(&self.file);
+        let mut pos = self.scanned;
+        loop {
+            let mut head = [0u8; 2];
+            if r.read_exact(&mut head).is_err() {
+                break;
+            }
+            let mut digest = vec![0u8; head[1] as usize];
+            let mut len = [0u8; 4];
+            if r.read_exact(&mut digest).is_err() || r.read_exact(&mut len).is_err() {
+                break;
+            }
+            let len = u32::from_le_bytes(len);
+            let data_at = pos + 2 + digest.len() as u64 + 4;
+            if data_at + u64::from(len) > end {
+                break;
+            }
+            r.seek_relative(i64::from(len))?;
+            let digest = String::from_utf8(digest).map_err(|_| anyhow!("corrupt ledger log"))?;
+            match head[0] {
+                OP_PUT => {
+                    self.live.insert(digest, (data_at, len));
+                }
+                OP_DELETE => {
+                    self.live.remove(&digest);
+                }
+                op => return Err(anyhow!("corrupt ledger log: unknown op {op} at {pos}")),
+            }
+            pos = data_at + u64::from(len);
+        }
+        self.scanned = pos;
+        Ok(pos)
+    }
+
+    fn read_at(&mut self, off: u64, len: u32) -> Result<Vec<u8>> {
+        self.file.seek(SeekFrom::Start(off))?;
+        let mut buf = vec![0u8; len as usize];
+        self.file.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Append one record; the caller holds the ledger dir lock.
+    fn append(&mut self, op: u8, digest: &str, data: &[u8]) -> Result<()> {
+        self.catch_up()?;
+        let rec = encode(op, digest, data)?;
+        self.file.write_all(&rec)?;
+        self.file.sync_data()?;
+        let start = self.file.metadata()?.len() - rec.len() as u64;
+        self.scanned = start + rec.len() as u64;
+        if op == OP_PUT {
+            let len = u32::try_from(data.len()).map_err(|_| anyhow!("object too large"))?;
+            self.live
+                .insert(digest.to_string(), (start + header_len(digest), len));
+        } else {
+            self.live.remove(digest);
+        }
+        Ok(())
+    }
+}
+
+impl LedgerStore for LogStore {
+    fn put(&self, digest: &str, bytes: &[u8]) -> Result<()> {
+        let _held = super::lock::exclusive(&self.dir)?;
+        let mut inner = self.lock()?;
+        inner.catch_up()?;
+        if inner.live.contains_key(digest) {
+            return Ok(());
+        }
+        inner.append(OP_PUT, digest, bytes)
+    }
+
+    fn get(&self, digest: &str) -> Result<Option<Vec<u8>>> {
+        let mut inner = self.lock()?;
+        if !inner.live.contains_key(digest) {
+            inner.catch_up()?;
+        }
+        match inner.live.get(digest).copied() {
+            Some((off, len)) => Ok(Some(inner.read_at(off, len)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn has(&self, digest: &str) -> Result<bool> {
+        let mut inner = self.lock()?;
+        inner.catch_up()?;
+        Ok(inner.live.contains_key(digest))
+    }
+
+    fn iter(&self) -> Result<Box<dyn Iterator<Item = Result<String>> + '_>> {
+        let digests: Vec<String> = {
+            let mut inner = self.lock()?;
+            inner.catch_up()?;
+            inner.live.keys().cloned().collect()
+        };
+        Ok(Box::new(digests.into_iter().map(Ok)))
+    }
+
+    fn delete(&self, digest: &str) -> Result<bool> {
+        let _held = super::lock::exclusive(&self.dir)?;
+        let mut inner = self.lock()?;
+        inner.catch_up()?;
+        if !inner.live.contains_key(digest) {
+            return Ok(false);
+        }
+        inner.append(OP_DELETE, digest, &[])?;
+        drop(inner);
+        Ok(true)
+    }
+
+    /// Rewrite the log with only live records.
+    fn compact(&self) -> Result<()> {
+        let _held = super::lock::exclusive(&self.dir)?;
+        let mut inner = self.lock()?;
+        inner.catch_up()?;
+        let tmp = inner.path.with_extension("log.tmp");
+        let mut out = File::create(&tmp)?;
+        let mut live = HashMap::new();
+        let mut offset = 0u64;
+        let mut digests: Vec<(String, (u64, u32))> =
+            inner.live.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        digests.sort_by_key(|(_, (off, _))| *off);
+        for (digest, (off, len)) in digests {
+            let data = inner.read_at(off, len)?;
+            let rec = encode(OP_PUT, &digest, &data)?;
+            out.write_all(&rec)?;
+            live.insert(digest.clone(), (offset + header_len(&digest), len));
+            offset += rec.len() as u64;
+        }
+        out.sync_all()?;
+        std::fs::rename(&tmp, &inner.path)?;
+        inner.file = open_log(&inner.path)?;
+        inner.scanned = offset;
+        inner.live = live;
+        drop(inner);
+        Ok(())
+    }
+}
@@ -2,4 +2,11 @@ pub mod receipt;
 pub mod schema;
 pub mod env_meta;
 pub mod identity;
-
+pub mod jcs;
+pub mod merkle;
+pub mod sth;
+pub mod ops;
+pub mod ledger;
+pub mod gateway;
+pub mod httpsig;
+pub mod sync;
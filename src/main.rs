@@ -2,27 +2,22 @@
 // Scope the allows to this file only—core libs remain strict.
 #![allow(clippy::similar_names, clippy::uninlined_format_args)]
 
-mod env_meta;
-mod identity;
-pub mod receipt;
-pub mod schema;
-mod ledger;
-mod gateway;
-mod sync;
-
-use crate::env_meta::collect_env_metadata;
-use crate::identity::resolve_actor_did;
-use crate::schema::{validate_provenance, validate_receipt as validate_receipt_schema};
+use vaultmesh::env_meta::collect_env_metadata;
+use vaultmesh::identity::{self, resolve_actor_did};
+use vaultmesh::merkle;
+use vaultmesh::ops::{self, Actor, Build, Env, Merkle, Op, Receipt, Sign};
+use vaultmesh::schema::{validate_provenance, validate_receipt as validate_receipt_schema};
+use vaultmesh::sync::{daemon, peers, pull, reconcile, PeerInfo, TrustLevel};
+use vaultmesh::{gateway, httpsig, ledger, receipt, schema, sth};
 use anyhow::{anyhow, Result};
 use base64::{engine::general_purpose, Engine as _};
 use blake3::Hasher;
 use chrono::Utc;
 use clap::{Parser, Subcommand, ValueEnum};
-use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature, Signer, Verifier};
-use serde::{Deserialize, Serialize};
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature, Signer};
+use serde::Deserialize;
 use serde_json::{json, Value};
 use std::io::Write as _;
-use std::collections::{BTreeMap, HashMap};
 use std::fs;
 
 #[derive(Parser)]
@@ -52,7 +47,7 @@ enum Cmd {
         #[command(subcommand)]
         cmd: HashCmd,
     },
-    /// Build a daily Merkle root from receipts in a directory
+    /// Build a daily Merkle root from receipts in a directory, with a tree head signed by the actor key
     Seal {
         /// Date (YYYY-MM-DD)
         #[arg(long)]
@@ -63,6 +58,9 @@ enum Cmd {
         /// Output path for root JSON
         #[arg(long)]
         out: String,
+        /// Tree mode: sorted-pair (legacy) | rfc6962
+        #[arg(long, default_value = "sorted-pair")]
+        alg: merkle::Alg,
     },
     /// Anchor a receipt by computing its Merkle path from a directory
     Anchor {
@@ -78,6 +76,27 @@ enum Cmd {
         /// Output path for anchored receipt
         #[arg(long)]
         out: String,
+        /// Tree mode; must match the one used by `seal`
+        #[arg(long, default_value = "sorted-pair")]
+        alg: merkle::Alg,
+    },
+    /// Prove that a newer sealed root is an append-only extension of an older one
+    Consistency {
+        /// Path to the older root JSON (from `seal --alg rfc6962`)
+        #[arg(long)]
+        old: String,
+        /// Path to the newer root JSON
+        #[arg(long)]
+        new: String,
+        /// Directory containing ALL receipts covered by the newer root (emits a proof)
+        #[arg(long, conflicts_with = "proof")]
+        dir: Option<String>,
+        /// Path to a previously emitted consistency proof JSON (verify only)
+        #[arg(long, required_unless_present = "dir")]
+        proof: Option<String>,
+        /// Output path for the consistency proof JSON
+        #[arg(long)]
+        out: Option<String>,
     },
     /// Sign a finalized receipt with an ed25519 key
     Sign {
@@ -99,10 +118,18 @@ enum Cmd {
         /// Path to root JSON
         #[arg(long)]
         root: String,
+        /// Gateway inclusion proof (`/v1/proof/:digest`); `--root` is then `/v1/sth` output
+        #[arg(long)]
+        proof: Option<String>,
         /// Perform extra checks (capability present, approvals exist)
         #[arg(long, default_value_t = false)]
         strict: bool,
     },
+    /// Receipt chains: each finalized receipt links to the previous one for its target
+    Chain {
+        #[command(subcommand)]
+        cmd: ChainCmd,
+    },
     /// Glue receipts: identity+env+signature
     Glue {
         #[command(subcommand)]
@@ -118,6 +145,25 @@ enum Cmd {
         /// Address to bind, e.g., 127.0.0.1:8080
         #[arg(long, default_value = "127.0.0.1:8080")]
         addr: String,
+        /// Pull from full and read-only peers in the background this often (e.g. 60s, 5m, 1h)
+        #[arg(long)]
+        sync_interval: Option<String>,
+        /// Ingest unsigned pushes (local development only; never on a shared node)
+        #[arg(long)]
+        allow_unsigned: bool,
+        /// host[:port] peers reach this node at; signed pushes must be addressed to it (default: --addr, unless it binds every interface)
+        #[arg(long)]
+        authority: Option<String>,
+    },
+    /// Published JSON Schemas
+    Schema {
+        #[command(subcommand)]
+        cmd: SchemaCmd,
+    },
+    /// Peer registry the gateway checks pushes against
+    Peer {
+        #[command(subcommand)]
+        cmd: PeerCmd,
     },
     /// Peer synchronization
     Sync {
@@ -145,6 +191,28 @@ enum HashCmd {
     },
 }
 
+#[derive(Subcommand)]
+enum SchemaCmd {
+    /// Print a schema as JSON (receipt|provenance|ops-receipt|root)
+    Print { name: String },
+}
+
+#[derive(Subcommand)]
+enum ChainCmd {
+    /// Walk the chain of finalized receipts for a target and check every link
+    Verify {
+        /// `op.target` whose chain to verify
+        #[arg(long)]
+        target: String,
+        /// Directory containing the receipts (each a JSON file)
+        #[arg(long, default_value = ".")]
+        dir: String,
+        /// Expected leaf of the newest receipt (detects a dropped tail)
+        #[arg(long)]
+        head: Option<String>,
+    },
+}
+
 #[derive(Subcommand)]
 enum GlueCmd {
     /// Emit a minimal receipt for an artifact
@@ -180,23 +248,96 @@ enum GlueCmd {
 
 #[derive(Subcommand)]
 enum LedgerCmd {
-    /// Add one or more JSON files to the local ledger (~/.vaultmesh/ledger)
+    /// Add one or more JSON files to the local ledger (`VAULTMESH_LEDGER_DIR` or ~/.vaultmesh/ledger)
     Add {
         /// Files to add (receipt/provenance JSON)
         #[arg(required = true)]
         files: Vec<String>,
     },
     /// List entries in the local ledger
+    Ls {
+        /// Only entries recorded for this commit (SHA or prefix)
+        #[arg(long)]
+        commit: Option<String>,
+        /// Only entries recorded for this ref (e.g., refs/heads/main)
+        #[arg(long = "ref")]
+        git_ref: Option<String>,
+        /// Only entries of this kind (receipt|ops|provenance|unknown)
+        #[arg(long)]
+        kind: Option<String>,
+    },
+    /// Re-key stored objects under the digest of their canonical (RFC 8785) JSON
+    Migrate,
+    /// Check stored objects: digests, schemas, signatures, provenance refs, orphans
+    Fsck,
+    /// Delete objects selected by a retention policy
+    Gc {
+        /// Collect objects ingested before this age (e.g. 30d, 12h) or RFC3339 time
+        #[arg(long)]
+        older_than: Option<String>,
+        /// Collect only this kind (repeatable)
+        #[arg(long)]
+        kind: Vec<String>,
+        /// Keep receipts covered by this ledger tree head (`/v1/sth` output; repeatable)
+        #[arg(long)]
+        keep_sealed: Vec<String>,
+        /// Report what would be collected without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Write objects and their index to a signed tar archive
+    Export {
+        /// Only entries ingested since this date (YYYY-MM-DD, RFC3339, or an age like 30d)
+        #[arg(long)]
+        since: Option<String>,
+        #[arg(long, default_value = "ledger.tar")]
+        out: String,
+    },
+    /// Verify a ledger archive and add its objects to the local ledger
+    Import {
+        file: String,
+        /// Require the manifest to be signed by this did:key (default: any
+        /// registered full peer)
+        #[arg(long)]
+        signer: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PeerCmd {
+    /// Register a peer by the did:key of its actor key
+    Add {
+        id: String,
+        /// Peer gateway base URL
+        #[arg(long)]
+        url: String,
+        /// full | read-only | quarantine
+        #[arg(long, default_value = "quarantine")]
+        trust: TrustLevel,
+    },
+    /// List registered peers
     Ls,
+    /// Change a peer's trust level (full | read-only | quarantine)
+    SetTrust { id: String, trust: TrustLevel },
+    /// Remove a peer
+    Rm { id: String },
 }
 
 #[derive(Subcommand)]
 enum SyncCmd {
-    /// Pull a receipt bundle from a URL (existing stub may be present)
-    Pull { url: String },
+    /// Mirror a peer's ledger: fetch what it indexed since the last pull and verify each object
+    Pull { #[arg(long)] peer: String },
+    /// Find the symmetric difference with a peer's ledger by range fingerprints and fetch what only the peer holds
+    Reconcile {
+        #[arg(long)]
+        peer: String,
+        /// Report the difference without fetching anything
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Push a local bundle to a peer for verification and ingestion
     Push { url: String, #[arg(long)] receipt: String, #[arg(long)] provenance: String },
-    /// Ask a peer to return a stored receipt by digest and verify it locally
+    /// Ask a peer to return a stored receipt by digest and verify it and its inclusion proof locally
     Verify { url: String, #[arg(long)] digest: String },
 }
 
@@ -207,86 +348,6 @@ enum ProvenanceMode {
     Braid,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-struct Receipt {
-    id: String,
-    ts: String,
-    actor: Actor,
-    op: Op,
-    build: Build,
-    env: Env,
-    sign: Sign,
-    leaf: String,
-    merkle: Merkle,
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug)]
-struct Actor {
-    id: String,
-    cap: Vec<String>,
-    sig: String,
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
-struct Op {
-    kind: String,
-    target: String,
-    #[serde(default)]
-    risk: Option<String>,
-    #[serde(default)]
-    change_window: Option<String>,
-    #[serde(default)]
-    approvals: Vec<String>,
-    plan_hash: String,
-    apply_hash: String,
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug)]
-struct Build {
-    repo: String,
-    commit: String,
-    binary_hash: String,
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
-struct Env {
-    #[serde(default)]
-    ci: Option<String>,
-    #[serde(default)]
-    runner: Option<String>,
-    #[serde(default)]
-    tf_version: Option<String>,
-    #[serde(default)]
-    plugins: Option<Vec<String>>,
-    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
-    entries: BTreeMap<String, String>,
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug)]
-struct Sign {
-    alg: String,
-    #[serde(rename = "sig")]
-    signature: String,
-    #[serde(rename = "pub")]
-    public_key: String,
-}
-impl Sign {
-    fn none() -> Self {
-        Self {
-            alg: "none".into(),
-            signature: String::new(),
-            public_key: String::new(),
-        }
-    }
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
-struct Merkle {
-    date: String,
-    path: Vec<String>,
-    root: String,
-}
-
 #[derive(Subcommand)]
 enum ReceiptCmd {
     /// Emit a pre-apply receipt from a Terraform plan JSON
@@ -308,6 +369,12 @@ enum ReceiptCmd {
         commit: String,
         #[arg(long, default_value = "dev-binary")]
         binary_hash: String,
+        /// Leaf of the previous receipt for --target
+        #[arg(long, conflicts_with = "prev_dir")]
+        prev: Option<String>,
+        /// Directory of earlier receipts; links to the head of the chain for --target
+        #[arg(long)]
+        prev_dir: Option<String>,
         #[arg(long)]
         out: String,
     },
@@ -335,77 +402,6 @@ fn blake3_hex(bytes: &[u8]) -> String {
     hex::encode(h.finalize().as_bytes())
 }
 
-fn hex_concat_ordered(a_hex: &str, b_hex: &str) -> Vec<u8> {
-    let (a, b) = if a_hex <= b_hex {
-        (a_hex, b_hex)
-    } else {
-        (b_hex, a_hex)
-    };
-    let mut bytes = Vec::with_capacity(a.len() / 2 + b.len() / 2);
-    bytes.extend(hex::decode(a).expect("hex decode a"));
-    bytes.extend(hex::decode(b).expect("hex decode b"));
-    bytes
-}
-
-fn to_value<T: Serialize>(t: &T) -> Value {
-    serde_json::to_value(t).expect("serialize")
-}
-
-fn remove_leaf_and_merkle(mut v: Value) -> Value {
-    if let Value::Object(ref mut m) = v {
-        m.remove("leaf");
-        m.remove("merkle");
-    }
-    v
-}
-
-fn remove_leaf_merkle_and_sig(mut v: Value) -> Value {
-    if let Value::Object(ref mut m) = v {
-        m.remove("leaf");
-        m.remove("merkle");
-        if let Some(Value::Object(ref mut s)) = m.get_mut("sign") {
-            s.remove("sig");
-        }
-    }
-    v
-}
-
-fn sort_json(v: Value) -> Value {
-    match v {
-        Value::Object(map) => {
-            let mut b = BTreeMap::new();
-            for (k, val) in map {
-                b.insert(k, sort_json(val));
-            }
-            Value::Object(b.into_iter().collect())
-        }
-        Value::Array(arr) => Value::Array(arr.into_iter().map(sort_json).collect()),
-        _ => v,
-    }
-}
-
-fn canonical_payload_json_v2<T: Serialize>(t: &T) -> String {
-    let v = to_value(t);
-    let v = remove_leaf_merkle_and_sig(v);
-    let v = sort_json(v);
-    serde_json::to_string(&v).unwrap()
-}
-
-fn canonical_payload_json_legacy<T: Serialize>(t: &T) -> String {
-    let v = to_value(t);
-    let v = remove_leaf_and_merkle(v);
-    let v = sort_json(v);
-    serde_json::to_string(&v).unwrap()
-}
-
-fn canonical_leaf_hex<T: Serialize>(t: &T) -> String {
-    blake3_hex(canonical_payload_json_v2(t).as_bytes())
-}
-
-fn canonical_leaf_hex_legacy<T: Serialize>(t: &T) -> String {
-    blake3_hex(canonical_payload_json_legacy(t).as_bytes())
-}
-
 fn blake3_file_hex(p: &std::path::Path) -> Result<String> {
     use std::io::Read;
     let mut f = std::fs::File::open(p)?;
@@ -422,44 +418,146 @@ fn blake3_file_hex(p: &std::path::Path) -> Result<String> {
 }
 
 // ---------- Merkle ----------
-fn build_merkle(leaves: &[String]) -> (String, HashMap<String, Vec<String>>) {
-    if leaves.is_empty() {
-        return (String::new(), HashMap::new());
+/// Collect receipt leaves from `dir` in the order the tree mode expects.
+/// `sorted-pair` sorts by leaf hex; `rfc6962` sorts by receipt id (ULID), then
+/// `ts`, then leaf, so that the same directory gives the same tree on every
+/// node and a later seal over it extends the earlier tree.
+fn collect_leaves(dir: &str, alg: merkle::Alg) -> Result<Vec<String>> {
+    let mut recs: Vec<(String, String, String)> = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let p = entry?.path();
+        if p.extension().and_then(|s| s.to_str()) == Some("json") {
+            let rec = read_ops_receipt(&p)?;
+            recs.push((rec.id, rec.ts, rec.leaf));
+        }
+    }
+    match alg {
+        merkle::Alg::SortedPair => recs.sort_by(|a, b| a.2.cmp(&b.2)),
+        merkle::Alg::Rfc6962 => recs.sort(),
     }
-    let mut layer = leaves.to_vec();
-    let mut paths: HashMap<String, Vec<String>> = HashMap::new();
+    Ok(recs.into_iter().map(|(_, _, leaf)| leaf).collect())
+}
 
-    // Initialize paths map
-    for l in &layer {
-        paths.entry(l.clone()).or_default();
+/// Finalized receipts for `target` in `dir`, in chain order (oldest first).
+/// Fails on a non-canonical or duplicate leaf, or unless they form a single
+/// unbroken chain ([`ops::chain`]).
+fn chain_receipts(dir: &str, target: &str) -> Result<Vec<Receipt>> {
+    let mut recs = Vec::new();
+    let mut leaves = std::collections::HashSet::new();
+    for entry in fs::read_dir(dir)? {
+        let p = entry?.path();
+        if p.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+        let rec = read_ops_receipt(&p)?;
+        if rec.op.target != target || rec.op.apply_hash.is_empty() {
+            continue;
+        }
+        if rec.leaf_form().is_none() {
+            return Err(anyhow!("{}: leaf mismatch: receipt tampered or not canonical", p.display()));
+        }
+        if !leaves.insert(rec.leaf.clone()) {
+            return Err(anyhow!("{}: duplicate receipt {}", p.display(), rec.leaf));
+        }
+        recs.push(rec);
     }
+    ops::chain(recs)
+}
 
-    let mut next_layer;
-    while layer.len() > 1 {
-        next_layer = Vec::new();
-        for chunk in layer.chunks(2) {
-            let (left, right) = if chunk.len() == 2 {
-                (chunk[0].clone(), chunk[1].clone())
-            } else {
-                (chunk[0].clone(), chunk[0].clone()) // duplicate odd leaf
-            };
-            let parent_hex = blake3_hex(&hex_concat_ordered(&left, &right));
-            // record sibling for paths
-            paths.entry(left.clone()).or_default().push(right.clone());
-            paths.entry(right.clone()).or_default().push(left.clone());
-            next_layer.push(parent_hex);
+/// `30d` / `12h` / `45m` before now, a `YYYY-MM-DD` date (UTC midnight), or
+/// an RFC3339 instant.
+fn parse_cutoff(s: &str) -> Result<chrono::DateTime<chrono::Utc>> {
+    if let Ok(t) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Ok(t.with_timezone(&chrono::Utc));
+    }
+    if let Ok(d) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(d.and_time(chrono::NaiveTime::MIN).and_utc());
+    }
+    let (n, unit) = s.split_at(s.len().saturating_sub(1));
+    let n: i64 = n.parse().map_err(|_| anyhow!("bad age: {} (expected e.g. 30d, 12h, 45m or RFC3339)", s))?;
+    let age = match unit {
+        "d" => chrono::Duration::days(n),
+        "h" => chrono::Duration::hours(n),
+        "m" => chrono::Duration::minutes(n),
+        _ => return Err(anyhow!("bad age unit in {} (expected d, h or m)", s)),
+    };
+    Ok(chrono::Utc::now() - age)
+}
+
+/// Read an ops receipt, checking it against the published schema first.
+fn read_ops_receipt(path: &std::path::Path) -> Result<Receipt> {
+    let v: Value = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+    schema::validate_ops_receipt(&v).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+    Ok(serde_json::from_value(v)?)
+}
+
+/// Check an ops receipt against a gateway's inclusion proof and tree head.
+/// Gateway tree leaves are the stored documents' digests, so the proof must be
+/// for [`ledger::ops_digest`] of the receipt, not for its `leaf`. `root_doc` is
+/// `/v1/sth` output (signature checked) or a response carrying `merkle_root`.
+fn verify_ledger_proof(rec: &Receipt, root_doc: Value, root: &str, proof: &str) -> Result<()> {
+    let proof: ledger::InclusionProof = serde_json::from_slice(&read(proof)?)?;
+    check_ledger_proof(&proof, &ledger::ops_digest(rec)?, root_doc, root)
+}
+
+/// Check that `proof` is for `digest` and folds to the root of `root_doc`
+/// (read from `root`), as [`verify_ledger_proof`] describes.
+fn check_ledger_proof(proof: &ledger::InclusionProof, digest: &str, root_doc: Value, root: &str) -> Result<()> {
+    if proof.digest != digest {
+        return Err(anyhow!(
+            "proof is for {}, receipt is stored as {}",
+            proof.digest,
+            digest
+        ));
+    }
+    let (root_hex, size) = if root_doc.get("sig").is_some() {
+        let head: sth::SignedTreeHead = serde_json::from_value(root_doc)
+            .map_err(|e| anyhow!("{}: not a signed tree head: {}", root, e))?;
+        head.verify()?;
+        if head.alg != proof.alg {
+            return Err(anyhow!(
+                "merkle alg mismatch: proof {} vs tree head {}",
+                proof.alg,
+                head.alg
+            ));
         }
-        layer = next_layer;
+        (head.root, Some(head.size))
+    } else {
+        let hex = root_doc
+            .get("merkle_root")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("{}: neither a tree head nor a merkle_root", root))?;
+        (hex.to_string(), None)
+    };
+    if proof.root != root_hex || size.is_some_and(|n| n != proof.size as u64) {
+        return Err(anyhow!("proof is not against the tree head in {}", root));
     }
-    (layer[0].clone(), paths)
+    if !proof.verify()? {
+        return Err(anyhow!("path->root mismatch"));
+    }
+    Ok(())
 }
 
-fn fold_path_to_root(leaf: &str, path: &[String]) -> String {
-    let mut cur = leaf.to_string();
-    for sib in path {
-        cur = blake3_hex(&hex_concat_ordered(&cur, sib));
+/// Read `{root, count, alg}` from a sealed root document; only `rfc6962`
+/// roots support consistency proofs.
+fn read_root_doc(path: &str) -> Result<(String, usize)> {
+    let doc: Value = serde_json::from_slice(&read(path)?)?;
+    schema::validate_root(&doc).map_err(|e| anyhow!("{}: {}", path, e))?;
+    let alg: merkle::Alg = serde_json::from_value(doc.get("alg").cloned().unwrap_or(Value::Null))
+        .unwrap_or_default();
+    if alg != merkle::Alg::Rfc6962 {
+        return Err(anyhow!("{}: consistency proofs require an rfc6962 root", path));
     }
-    cur
+    let root = doc
+        .get("root")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("{}: missing root", path))?;
+    let count = doc
+        .get("count")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| anyhow!("{}: missing count", path))?;
+    Ok((root.to_string(), usize::try_from(count)?))
 }
 
 // ---------- Main ----------
@@ -478,8 +576,14 @@ fn main() -> Result<()> {
                 repo,
                 commit,
                 binary_hash,
+                prev,
+                prev_dir,
                 out,
             } => {
+                let prev = match prev_dir {
+                    Some(dir) => chain_receipts(&dir, &target)?.last().map(|r| r.leaf.clone()),
+                    None => prev,
+                };
                 let plan_hash = blake3_hex(&read(&plan)?);
                 let id = ulid::Ulid::new().to_string();
                 let ts = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
@@ -501,10 +605,8 @@ fn main() -> Result<()> {
                 }
 
                 // Resolve actor DID dynamically
-                let actor_did = resolve_actor_did().unwrap_or_else(|e| {
-                    eprintln!("⚠️  Warning: failed to resolve actor DID: {}", e);
-                    "did:placeholder".to_string()
-                });
+                let actor_did = resolve_actor_did()
+                    .map_err(|e| anyhow!("receipt emit: failed to resolve actor DID: {}", e))?;
 
                 // Collect CI/CD environment metadata
                 let env_meta = collect_env_metadata();
@@ -519,8 +621,10 @@ fn main() -> Result<()> {
                 };
 
                 let mut rec = Receipt {
+                    version: Some(schema::OPS_RECEIPT_VERSION.to_string()),
                     id,
                     ts,
+                    prev,
                     actor: Actor {
                         id: actor_did,
                         cap: vec![cap],
@@ -543,20 +647,16 @@ fn main() -> Result<()> {
                     env,
                     sign: Sign::none(),
                     leaf: String::new(),
-                    merkle: Merkle {
-                        date: String::new(),
-                        path: vec![],
-                        root: String::new(),
-                    },
+                    merkle: Merkle::default(),
                 };
-                rec.leaf = canonical_leaf_hex(&rec);
+                rec.leaf = ops::canonical_leaf_hex(&rec);
                 write(&out, &serde_json::to_string_pretty(&rec)?)?;
                 println!("EMITTED {}", out);
             }
             ReceiptCmd::Finalize { receipt, post, out } => {
-                let mut rec: Receipt = serde_json::from_slice(&read(&receipt)?)?;
+                let mut rec = read_ops_receipt(std::path::Path::new(&receipt))?;
                 rec.op.apply_hash = blake3_hex(&read(&post)?);
-                rec.leaf = canonical_leaf_hex(&rec);
+                rec.leaf = rec.canonical_leaf()?;
                 write(&out, &serde_json::to_string_pretty(&rec)?)?;
                 println!("FINALIZED {}", out);
             }
@@ -585,22 +685,24 @@ fn main() -> Result<()> {
                 println!("{}", blake3_hex(&bytes));
             }
         },
-        Cmd::Seal { date, dir, out } => {
-            let mut leaves: Vec<String> = Vec::new();
-            for entry in fs::read_dir(&dir)? {
-                let p = entry?.path();
-                if p.extension().and_then(|s| s.to_str()) == Some("json") {
-                    let rec: Receipt = serde_json::from_slice(&fs::read(&p)?)?;
-                    leaves.push(rec.leaf.clone());
-                }
-            }
-            leaves.sort(); // deterministic
-            let (root, _paths) = build_merkle(&leaves);
+        Cmd::Seal {
+            date,
+            dir,
+            out,
+            alg,
+        } => {
+            let leaves = collect_leaves(&dir, alg)?;
+            let root = merkle::root(alg, &leaves)?;
+            let kp = identity::load_actor_keypair()?;
+            let head = sth::sign(alg, leaves.len() as u64, &root, &kp)?;
             let root_doc = json!({
                 "date": date,
                 "root": root,
-                "count": leaves.len()
+                "count": leaves.len(),
+                "alg": alg,
+                "sth": head
             });
+            schema::validate_root(&root_doc)?;
             write(&out, &serde_json::to_string_pretty(&root_doc)?)?;
             println!("SEALED {}", out);
         }
@@ -609,31 +711,72 @@ fn main() -> Result<()> {
             dir,
             date,
             out,
+            alg,
         } => {
             // Build tree to compute path for the given receipt
-            let rec_bytes = read(&receipt)?;
-            let mut rec: Receipt = serde_json::from_slice(&rec_bytes)?;
-            let mut leaves: Vec<String> = Vec::new();
-            for entry in fs::read_dir(&dir)? {
-                let p = entry?.path();
-                if p.extension().and_then(|s| s.to_str()) == Some("json") {
-                    let r: Receipt = serde_json::from_slice(&fs::read(&p)?)?;
-                    leaves.push(r.leaf.clone());
-                }
-            }
-            leaves.sort();
-            let (root, paths) = build_merkle(&leaves);
-            let path = paths
-                .get(&rec.leaf)
+            let mut rec = read_ops_receipt(std::path::Path::new(&receipt))?;
+            let leaves = collect_leaves(&dir, alg)?;
+            let root = merkle::root(alg, &leaves)?;
+            let path = leaves
+                .iter()
+                .position(|l| *l == rec.leaf)
+                .and_then(|i| merkle::audit_path(alg, &leaves, i).transpose())
+                .transpose()?
                 .ok_or_else(|| anyhow!("leaf not found in set; ensure dir is the correct date"))?;
             rec.merkle = Merkle {
+                alg,
                 date,
-                path: path.clone(),
+                path,
                 root,
             };
             write(&out, &serde_json::to_string_pretty(&rec)?)?;
             println!("ANCHORED {}", out);
         }
+        Cmd::Consistency {
+            old,
+            new,
+            dir,
+            proof,
+            out,
+        } => {
+            let (old_root, old_size) = read_root_doc(&old)?;
+            let (new_root, new_size) = read_root_doc(&new)?;
+            let proof: Vec<String> = if let Some(dir) = dir {
+                let leaves = collect_leaves(&dir, merkle::Alg::Rfc6962)?;
+                if leaves.len() != new_size || merkle::root(merkle::Alg::Rfc6962, &leaves)? != new_root {
+                    return Err(anyhow!("{} does not reproduce the newer root", dir));
+                }
+                merkle::consistency_proof(&leaves, old_size)?
+            } else {
+                let path = proof.ok_or_else(|| anyhow!("either --dir or --proof is required"))?;
+                let doc: Value = serde_json::from_slice(&read(&path)?)?;
+                if doc.get("old_size").and_then(Value::as_u64) != Some(old_size as u64)
+                    || doc.get("new_size").and_then(Value::as_u64) != Some(new_size as u64)
+                {
+                    return Err(anyhow!("proof sizes do not match the root documents"));
+                }
+                serde_json::from_value(doc.get("proof").cloned().unwrap_or(Value::Null))?
+            };
+            if !merkle::verify_consistency(old_size, new_size, &old_root, &new_root, &proof)? {
+                return Err(anyhow!(
+                    "consistency check failed: {} is not an append-only extension of {}",
+                    new,
+                    old
+                ));
+            }
+            if let Some(out) = out {
+                let proof_doc = json!({
+                    "alg": merkle::Alg::Rfc6962,
+                    "old_size": old_size,
+                    "old_root": old_root,
+                    "new_size": new_size,
+                    "new_root": new_root,
+                    "proof": proof
+                });
+                write(&out, &serde_json::to_string_pretty(&proof_doc)?)?;
+            }
+            println!("CONSISTENT {} -> {}", old_size, new_size);
+        }
         Cmd::Sign { receipt, key, out } => {
             #[derive(Deserialize)]
             struct KeyJson {
@@ -657,46 +800,62 @@ fn main() -> Result<()> {
                 SecretKey::from_bytes(&sec_bytes).map_err(|e| anyhow!("bad secret: {}", e))?;
             let kp = Keypair { secret, public };
 
-            let mut rec: Receipt = serde_json::from_slice(&read(&receipt)?)?;
-            // populate signing metadata before canonicalizing
+            let mut rec = read_ops_receipt(std::path::Path::new(&receipt))?;
+            // populate signing metadata before canonicalizing; signing always
+            // produces the current version's leaf form
+            rec.version = Some(schema::OPS_RECEIPT_VERSION.to_string());
             rec.sign = Sign {
                 alg: "ed25519".into(),
                 signature: String::new(),
                 public_key: general_purpose::STANDARD.encode(kp.public.as_bytes()),
             };
-            let msg = canonical_payload_json_v2(&rec);
+            let msg = ops::canonical_payload_json(&rec);
             let sig: Signature = kp.sign(msg.as_bytes());
             rec.sign.signature = general_purpose::STANDARD.encode(sig.to_bytes());
             // ensure leaf reflects signed payload
-            rec.leaf = canonical_leaf_hex(&rec);
+            rec.leaf = ops::canonical_leaf_hex(&rec);
             write(&out, &serde_json::to_string_pretty(&rec)?)?;
             println!("SIGNED {}", out);
         }
         Cmd::Verify {
             receipt,
             root,
+            proof,
             strict,
         } => {
-            let rec: Receipt = serde_json::from_slice(&read(&receipt)?)?;
-            let computed_leaf = canonical_leaf_hex(&rec);
-            let mut legacy_ok = false;
-            if computed_leaf != rec.leaf {
-                let legacy_leaf = canonical_leaf_hex_legacy(&rec);
-                if legacy_leaf == rec.leaf {
-                    legacy_ok = true;
-                    eprintln!("warning: legacy leaf accepted (included sign.sig)");
-                } else {
-                    return Err(anyhow!("leaf mismatch: receipt tampered or not canonical"));
-                }
+            let rec = read_ops_receipt(std::path::Path::new(&receipt))?;
+            let form = rec
+                .leaf_form()
+                .ok_or_else(|| anyhow!("leaf mismatch: receipt tampered or not canonical"))?;
+            if form == ops::LeafForm::Legacy {
+                eprintln!("warning: legacy leaf accepted (included sign.sig)");
             }
             let root_doc: Value = serde_json::from_slice(&read(&root)?)?;
-            let root_hex = root_doc
-                .get("root")
-                .and_then(|v| v.as_str())
-                .ok_or_else(|| anyhow!("invalid root.json"))?;
-            let folded = fold_path_to_root(&rec.leaf, &rec.merkle.path);
-            if folded != root_hex {
-                return Err(anyhow!("path->root mismatch"));
+            if let Some(proof) = proof {
+                verify_ledger_proof(&rec, root_doc, &root, &proof)?;
+            } else {
+                if root_doc.get("merkle_root").is_none() {
+                    schema::validate_root(&root_doc).map_err(|e| anyhow!("{}: {}", root, e))?;
+                }
+                // Accept sealed root docs (`root`) and gateway responses (`merkle_root`).
+                let root_hex = root_doc
+                    .get("root")
+                    .or_else(|| root_doc.get("merkle_root"))
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow!("invalid root.json"))?;
+                if let Some(doc_alg) = root_doc.get("alg") {
+                    let doc_alg: merkle::Alg = serde_json::from_value(doc_alg.clone())?;
+                    if doc_alg != rec.merkle.alg {
+                        return Err(anyhow!(
+                            "merkle alg mismatch: receipt {} vs root {}",
+                            rec.merkle.alg,
+                            doc_alg
+                        ));
+                    }
+                }
+                if !merkle::verify_path(rec.merkle.alg, &rec.leaf, &rec.merkle.path, root_hex)? {
+                    return Err(anyhow!("path->root mismatch"));
+                }
             }
             if strict {
                 if rec.actor.cap.is_empty() {
@@ -709,32 +868,39 @@ fn main() -> Result<()> {
                     return Err(anyhow!("strict: missing plan/apply hashes"));
                 }
                 // Require ed25519 signature
-                if rec.sign.alg.to_lowercase() != "ed25519"
-                    || rec.sign.signature.is_empty()
-                    || rec.sign.public_key.is_empty()
-                {
-                    return Err(anyhow!("strict: missing ed25519 signature"));
-                }
-                let pub_bytes = general_purpose::STANDARD
-                    .decode(rec.sign.public_key.as_bytes())
-                    .map_err(|e| anyhow!("strict: bad public b64: {}", e))?;
-                let sig_bytes = general_purpose::STANDARD
-                    .decode(rec.sign.signature.as_bytes())
-                    .map_err(|e| anyhow!("strict: bad signature b64: {}", e))?;
-                let pk = PublicKey::from_bytes(&pub_bytes)
-                    .map_err(|e| anyhow!("strict: bad public: {}", e))?;
-                let sig = Signature::from_bytes(&sig_bytes)
-                    .map_err(|e| anyhow!("strict: bad signature: {}", e))?;
-                let msg = if legacy_ok {
-                    canonical_payload_json_legacy(&rec)
-                } else {
-                    canonical_payload_json_v2(&rec)
-                };
-                pk.verify(msg.as_bytes(), &sig)
-                    .map_err(|_| anyhow!("strict: signature verification failed"))?;
+                rec.verify_signature(form)
+                    .map_err(|e| anyhow!("strict: {}", e))?;
             }
             println!("VERIFIED ✅");
         }
+        Cmd::Schema { cmd } => match cmd {
+            SchemaCmd::Print { name } => {
+                let schema = schema::by_name(&name).ok_or_else(|| {
+                    anyhow!("unknown schema: {} (expected {})", name, schema::NAMES.join("|"))
+                })?;
+                println!("{}", serde_json::to_string_pretty(schema)?);
+            }
+        },
+        Cmd::Chain { cmd } => match cmd {
+            ChainCmd::Verify { target, dir, head } => {
+                let chain = chain_receipts(&dir, &target)?;
+                for rec in &chain {
+                    println!("{}  {}", rec.id, rec.leaf);
+                }
+                let last = chain.last().map(|r| r.leaf.as_str());
+                if let Some(head) = head {
+                    if last != Some(head.as_str()) {
+                        return Err(anyhow!(
+                            "chain for {} ends at {}, expected {}",
+                            target,
+                            last.unwrap_or("(empty)"),
+                            head
+                        ));
+                    }
+                }
+                println!("CHAIN OK {} ({} receipts)", target, chain.len());
+            }
+        },
         Cmd::Glue { cmd } => match cmd {
             GlueCmd::Emit {
                 kind,
@@ -784,7 +950,8 @@ fn main() -> Result<()> {
                         let json_signed = serde_json::to_string_pretty(&signed)?;
                         println!("{}", json_signed);
                         if matches!(provenance_mode, ProvenanceMode::Braid) {
-                            let rcpt_hex = receipt::blake3_hex(json_signed.as_bytes());
+                            // ledger digest of the signed receipt (BLAKE3 over JCS bytes)
+                            let rcpt_hex = receipt::blake3_hex(&receipt::canonical_json_bytes(&signed));
                             // rewrite provenance with receipt_digest included
                             let mut prov_val = serde_json::to_value(prov)?;
                             if let serde_json::Value::Object(ref mut m) = prov_val {
@@ -845,55 +1012,193 @@ fn main() -> Result<()> {
         },
         Cmd::Ledger { cmd } => match cmd {
             LedgerCmd::Add { files } => {
-                let home = dirs::home_dir().ok_or_else(|| anyhow!("no home dir"))?;
-                let dir = home.join(".vaultmesh").join("ledger");
-                std::fs::create_dir_all(&dir)?;
                 for f in files {
-                    let bytes = read(&f)?;
-                    // try parse to decide receipt/provenance and validate
-                    if let Ok(v) = serde_json::from_slice::<serde_json::Value>(&bytes) {
-                        let ok = validate_receipt_schema(&v).is_ok() || validate_provenance(&v).is_ok();
-                        if !ok {
-                            return Err(anyhow!("{}: not a valid receipt/provenance", f));
-                        }
-                    }
-                    let digest = blake3_hex(&bytes);
-                    let path = dir.join(format!("{digest}.json"));
-                    std::fs::write(&path, &bytes)?;
+                    let digest = ledger::add_document(&read(&f)?).map_err(|e| anyhow!("{}: {}", f, e))?;
                     println!("{}  {}", digest, f);
                 }
             }
-            LedgerCmd::Ls => {
-                let home = dirs::home_dir().ok_or_else(|| anyhow!("no home dir"))?;
-                let dir = home.join(".vaultmesh").join("ledger");
-                if !dir.exists() {
-                    return Ok(());
-                }
-                let mut entries: Vec<_> = std::fs::read_dir(&dir)?.collect::<Result<_, _>>()?;
-                entries.sort_by_key(std::fs::DirEntry::file_name);
-                for e in entries {
-                    let name = e.file_name().to_string_lossy().to_string();
-                    println!("{}", name);
+            LedgerCmd::Ls {
+                commit,
+                git_ref,
+                kind,
+            } => {
+                let filter = ledger::Filter {
+                    kind,
+                    commit,
+                    git_ref,
+                };
+                for e in ledger::query(&filter)? {
+                    println!(
+                        "{}  {}  {}  {}",
+                        e.digest,
+                        e.kind,
+                        e.commit.as_deref().unwrap_or("-"),
+                        e.git_ref.as_deref().unwrap_or("-")
+                    );
+                }
+            }
+            LedgerCmd::Migrate => {
+                let report = ledger::migrate_canonical()?;
+                for (old, new) in &report.rekeyed {
+                    println!("{}  ->  {}", old, new);
+                }
+                for d in &report.skipped {
+                    eprintln!("warning: {} missing or not JSON; left as is", d);
+                }
+                println!(
+                    "MIGRATED {} re-keyed, {} unchanged, {} skipped",
+                    report.rekeyed.len(),
+                    report.unchanged,
+                    report.skipped.len()
+                );
+                println!("ROOT {} ({} leaves)  ->  {} ({} leaves)", report.old_root.0, report.old_root.1, report.new_root.0, report.new_root.1);
+            }
+            LedgerCmd::Fsck => {
+                let report = ledger::fsck()?;
+                for (digest, problem) in &report.problems {
+                    println!("{}  {}", digest, problem);
+                }
+                if !report.is_ok() {
+                    return Err(anyhow!(
+                        "fsck: {} problem(s) in {} object(s)",
+                        report.problems.len(),
+                        report.checked
+                    ));
+                }
+                println!("FSCK OK {} objects", report.checked);
+            }
+            LedgerCmd::Gc {
+                older_than,
+                kind,
+                keep_sealed,
+                dry_run,
+            } => {
+                let mut sealed = Vec::new();
+                for path in &keep_sealed {
+                    let doc: Value = serde_json::from_slice(&read(path)?)?;
+                    // `seal` heads are over a receipt directory, not this ledger.
+                    if doc.get("sth").is_some() {
+                        return Err(anyhow!(
+                            "{}: a seal root doc covers a receipt directory; pass the ledger's /v1/sth",
+                            path
+                        ));
+                    }
+                    sealed.push(
+                        serde_json::from_value(doc)
+                            .map_err(|e| anyhow!("{}: not a signed tree head: {}", path, e))?,
+                    );
+                }
+                let policy = ledger::Retention {
+                    older_than: older_than.as_deref().map(parse_cutoff).transpose()?,
+                    kinds: kind,
+                    sealed,
+                };
+                let report = ledger::gc(&policy, dry_run)?;
+                for e in &report.collected {
+                    println!("{}  {}  {}", e.digest, e.kind, e.ts);
+                }
+                println!(
+                    "{} {} collected, {} kept (sealed), {} kept (referenced)",
+                    if dry_run { "GC DRY RUN" } else { "GC" },
+                    report.collected.len(),
+                    report.kept_sealed,
+                    report.kept_referenced
+                );
+            }
+            LedgerCmd::Export { since, out } => {
+                let kp = identity::load_actor_keypair()?;
+                let since = since.as_deref().map(parse_cutoff).transpose()?;
+                let manifest = ledger::archive::export(std::path::Path::new(&out), since, &kp)?;
+                println!("EXPORTED {} objects root {} -> {}", manifest.count, manifest.root, out);
+            }
+            LedgerCmd::Import { file, signer } => {
+                let report = ledger::archive::import(std::path::Path::new(&file), signer.as_deref())?;
+                for d in &report.added {
+                    println!("{}", d);
                 }
+                println!(
+                    "IMPORTED {} new, {} present, signed by {}",
+                    report.added.len(),
+                    report.present,
+                    report.manifest.node
+                );
             }
         },
-        Cmd::Gateway { addr } => {
+        Cmd::Gateway { addr, sync_interval, allow_unsigned, authority } => {
+            let sync_interval = sync_interval.as_deref().map(daemon::parse_interval).transpose()?;
+            let authority = match authority {
+                Some(a) => a,
+                None if addr.parse::<std::net::SocketAddr>().is_ok_and(|a| a.ip().is_unspecified()) => {
+                    return Err(anyhow!("--addr {} binds every interface; pass --authority <host:port> peers reach this node at", addr));
+                }
+                None => addr.clone(),
+            };
+            let opts = gateway::Options { allow_unsigned, authority: Some(authority), sync_interval };
             // Launch async gateway without #[tokio::main]
             let rt = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
-            rt.block_on(crate::gateway::run(&addr))?;
+            rt.block_on(gateway::run(&addr, opts))?;
         }
+        Cmd::Peer { cmd } => match cmd {
+            PeerCmd::Add { id, url, trust } => {
+                peers::add(PeerInfo { id: id.clone(), url, trust })?;
+                println!("PEER ADDED {} ({})", id, trust);
+            }
+            PeerCmd::Ls => {
+                for p in peers::load()? {
+                    println!("{}  {}  {}", p.id, p.trust, p.url);
+                }
+            }
+            PeerCmd::SetTrust { id, trust } => {
+                peers::set_trust(&id, trust)?;
+                println!("PEER {} -> {}", id, trust);
+            }
+            PeerCmd::Rm { id } => {
+                if !peers::remove(&id)? {
+                    return Err(anyhow!("no peer {}", id));
+                }
+                println!("PEER REMOVED {}", id);
+            }
+        },
         Cmd::Sync { cmd } => match cmd {
-            SyncCmd::Pull { url } => {
-                // Minimal: fetch receipt JSON and verify
-                let body = ureq::get(&url).call()?.into_string()?;
-                let v: serde_json::Value = serde_json::from_str(&body)?;
-                crate::schema::validate_receipt(&v)?;
-                let rcpt: crate::receipt::Receipt = serde_json::from_value(v.clone())?;
-                crate::receipt::verify_receipt(&rcpt)?;
-                let commit = rcpt.env.get("git_commit").cloned();
-                let rref = rcpt.env.get("git_ref").cloned();
-                let d = crate::ledger::add_json("receipt", body.as_bytes(), commit, rref)?;
-                println!("pulled and verified receipt: {}", d);
+            SyncCmd::Pull { peer } => {
+                let report = pull::pull(&peer)?;
+                for (digest, why) in &report.rejected {
+                    eprintln!("REJECTED {}: {}", digest, why);
+                }
+                println!(
+                    "PULLED {} {} from {} ({} present, {} rejected; cursor {}, peer size {})",
+                    report.fetched,
+                    if report.staged { "staged" } else { "new" },
+                    report.peer,
+                    report.present,
+                    report.rejected.len(),
+                    report.cursor.next,
+                    report.cursor.size
+                );
+            }
+            SyncCmd::Reconcile { peer, dry_run } => {
+                let report = reconcile::reconcile(&peer, dry_run)?;
+                for e in &report.diff.remote_only {
+                    println!("REMOTE {}  {}", e.digest, e.kind);
+                }
+                for e in &report.diff.local_only {
+                    println!("LOCAL  {}  {}", e.digest, e.kind);
+                }
+                for (digest, why) in &report.rejected {
+                    eprintln!("REJECTED {}: {}", digest, why);
+                }
+                println!(
+                    "{} {}: {} remote-only, {} local-only in {} rounds; {} {}, {} skipped, {} rejected",
+                    if dry_run { "RECONCILE DRY RUN" } else { "RECONCILED" },
+                    report.peer,
+                    report.diff.remote_only.len(),
+                    report.diff.local_only.len(),
+                    report.diff.rounds,
+                    report.fetched,
+                    if report.staged { "staged" } else { "fetched" },
+                    report.skipped,
+                    report.rejected.len()
+                );
             }
             SyncCmd::Push { url, receipt, provenance } => {
                 let mut base = url.trim_end_matches('/').to_string();
@@ -903,26 +1208,53 @@ fn main() -> Result<()> {
                 let p_bytes = std::fs::read(&provenance)?;
                 let r_json: serde_json::Value = serde_json::from_slice(&r_bytes)?;
                 let p_json: serde_json::Value = serde_json::from_slice(&p_bytes)?;
-                crate::schema::validate_receipt(&r_json)?;
-                crate::schema::validate_provenance(&p_json)?;
+                schema::validate_receipt(&r_json)?;
+                schema::validate_provenance(&p_json)?;
                 let payload = serde_json::json!({ "receipt": r_json, "provenance": p_json });
-                let resp = ureq::post(&verify_url)
+                let body = payload.to_string();
+                let kp = identity::load_actor_keypair()?;
+                let signed = httpsig::sign("POST", httpsig::authority_of(&verify_url), httpsig::path_of(&verify_url), body.as_bytes(), &kp);
+                let mut req = ureq::post(&verify_url)
                     .timeout(std::time::Duration::from_secs(20))
-                    .set("content-type", "application/json")
-                    .send_string(&payload.to_string())?;
+                    .set("content-type", "application/json");
+                for (name, value) in signed.pairs() {
+                    req = req.set(name, value);
+                }
+                let resp = req.send_string(&body)?;
                 let resp_text = resp.into_string()?;
                 println!("{}", resp_text);
             }
             SyncCmd::Verify { url, digest } => {
-                let mut base = url.trim_end_matches('/').to_string();
-                if !base.ends_with("/v1") { base.push_str("/v1"); }
+                let base = pull::api_base(&url);
                 let get_url = format!("{}/ledger/{}", base, digest);
                 let body = ureq::get(&get_url).call()?.into_string()?;
+                if receipt::blake3_hex(body.as_bytes()) != digest {
+                    return Err(anyhow!("peer returned content that does not hash to {}", digest));
+                }
                 let v: serde_json::Value = serde_json::from_str(&body)?;
-                crate::schema::validate_receipt(&v)?;
-                let rcpt: crate::receipt::Receipt = serde_json::from_value(v)?;
-                crate::receipt::verify_receipt(&rcpt)?;
-                println!("verified receipt from peer: {}", digest);
+                schema::validate_receipt(&v)?;
+                let rcpt: receipt::Receipt = serde_json::from_value(v)?;
+                receipt::verify_receipt(&rcpt)?;
+                // The proof must fold to the root of the peer's signed tree
+                // head, not just to the root it reports alongside the path.
+                let sth_url = format!("{}/sth", base);
+                let head: sth::SignedTreeHead =
+                    serde_json::from_str(&ureq::get(&sth_url).call()?.into_string()?)
+                        .map_err(|e| anyhow!("{}: not a signed tree head: {}", sth_url, e))?;
+                let registered = peers::load()?
+                    .into_iter()
+                    .find(|p| pull::api_base(&p.url) == base);
+                if let Some(peer) = registered.filter(|p| p.id != head.node) {
+                    return Err(anyhow!("{} signs as {}, not {}", base, head.node, peer.id));
+                }
+                let proof_url = format!("{}/proof/{}", base, digest);
+                let proof: ledger::InclusionProof =
+                    serde_json::from_str(&ureq::get(&proof_url).call()?.into_string()?)?;
+                check_ledger_proof(&proof, &digest, serde_json::to_value(&head)?, &sth_url)?;
+                println!(
+                    "verified receipt from peer: {} (leaf {} of {}, root {})",
+                    digest, proof.index, proof.size, proof.root
+                );
             }
         },
     }
@@ -0,0 +1,374 @@
+#![allow(clippy::missing_errors_doc, clippy::must_use_candidate)]
+//! Merkle trees over hex-encoded BLAKE3 leaves.
+//!
+//! Two tree modes are supported:
+//! - `sorted-pair` (legacy, v0.1 roots): sibling pairs are byte-ordered before
+//!   hashing and an odd node is paired with itself. Paths are bare sibling hashes.
+//! - `rfc6962`: RFC 6962 §2.1 tree hash with `0x00`/`0x01` leaf/node domain
+//!   separation, no duplication of odd nodes, and positional audit paths.
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+type Hash = [u8; 32];
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Alg {
+    #[default]
+    SortedPair,
+    Rfc6962,
+}
+
+impl fmt::Display for Alg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SortedPair => f.write_str("sorted-pair"),
+            Self::Rfc6962 => f.write_str("rfc6962"),
+        }
+    }
+}
+
+impl FromStr for Alg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sorted-pair" => Ok(Self::SortedPair),
+            "rfc6962" => Ok(Self::Rfc6962),
+            other => Err(anyhow!(
+                "unknown merkle alg: {other} (expected sorted-pair|rfc6962)"
+            )),
+        }
+    }
+}
+
+/// Position of a sibling relative to the node being folded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// One step of an audit path, ordered leaf → root.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PathStep {
+    /// RFC 6962 sibling with explicit position.
+    Positional { side: Side, hash: String },
+    /// Legacy sorted-pair sibling; position is implied by byte order.
+    Sibling(String),
+}
+
+/// Compute the root of `leaves` (in the given order).
+///
+/// An empty `sorted-pair` tree has an empty root; an empty `rfc6962` tree is
+/// the hash of the empty string, as in RFC 6962.
+pub fn root(alg: Alg, leaves: &[String]) -> Result<String> {
+    match alg {
+        Alg::SortedPair => {
+            if leaves.is_empty() {
+                return Ok(String::new());
+            }
+            let mut layer = leaves.to_vec();
+            while layer.len() > 1 {
+                layer = layer
+                    .chunks(2)
+                    .map(|c| sorted_parent(&c[0], c.get(1).unwrap_or(&c[0])))
+                    .collect::<Result<_>>()?;
+            }
+            Ok(layer.remove(0))
+        }
+        Alg::Rfc6962 => Ok(hex::encode(mth(&decode_leaves(leaves)?))),
+    }
+}
+
+/// Audit path for the leaf at `index`, or `None` if out of range.
+pub fn audit_path(alg: Alg, leaves: &[String], index: usize) -> Result<Option<Vec<PathStep>>> {
+    if index >= leaves.len() {
+        return Ok(None);
+    }
+    match alg {
+        Alg::SortedPair => {
+            let mut path = Vec::new();
+            let mut layer = leaves.to_vec();
+            let mut i = index;
+            while layer.len() > 1 {
+                let sib = layer.get(i ^ 1).unwrap_or(&layer[i]).clone();
+                path.push(PathStep::Sibling(sib));
+                layer = layer
+                    .chunks(2)
+                    .map(|c| sorted_parent(&c[0], c.get(1).unwrap_or(&c[0])))
+                    .collect::<Result<_>>()?;
+                i /= 2;
+            }
+            Ok(Some(path))
+        }
+        Alg::Rfc6962 => {
+            let hashes = decode_leaves(leaves)?;
+            let mut path = Vec::new();
+            rfc_path(index, &hashes, &mut path);
+            Ok(Some(path))
+        }
+    }
+}
+
+/// Fold `leaf` up through `path` and return the resulting root.
+pub fn fold_path(alg: Alg, leaf: &str, path: &[PathStep]) -> Result<String> {
+    match alg {
+        Alg::SortedPair => {
+            let mut cur = leaf.to_string();
+            for step in path {
+                let PathStep::Sibling(sib) = step else {
+                    return Err(anyhow!("sorted-pair path contains positional step"));
+                };
+                cur = sorted_parent(&cur, sib)?;
+            }
+            Ok(cur)
+        }
+        Alg::Rfc6962 => {
+            let mut cur = leaf_hash(&decode_hash(leaf)?);
+            for step in path {
+                let PathStep::Positional { side, hash } = step else {
+                    return Err(anyhow!("rfc6962 path step is missing its side"));
+                };
+                let sib = decode_hash(hash)?;
+                cur = match side {
+                    Side::Left => node_hash(&sib, &cur),
+                    Side::Right => node_hash(&cur, &sib),
+                };
+            }
+            Ok(hex::encode(cur))
+        }
+    }
+}
+
+/// True when `leaf` folds through `path` to `root`.
+pub fn verify_path(alg: Alg, leaf: &str, path: &[PathStep], root: &str) -> Result<bool> {
+    Ok(fold_path(alg, leaf, path)? == root)
+}
+
+/// RFC 6962 §2.1.2 consistency proof that the first `old_size` leaves of
+/// `leaves` form a prefix of the full tree.
+pub fn consistency_proof(leaves: &[String], old_size: usize) -> Result<Vec<String>> {
+    if old_size > leaves.len() {
+        return Err(anyhow!(
+            "old size {old_size} exceeds tree size {}",
+            leaves.len()
+        ));
+    }
+    if old_size == 0 || old_size == leaves.len() {
+        return Ok(Vec::new());
+    }
+    let hashes = decode_leaves(leaves)?;
+    let mut out = Vec::new();
+    subproof(old_size, &hashes, true, &mut out);
+    Ok(out.into_iter().map(hex::encode).collect())
+}
+
+/// Verify an RFC 6962 consistency proof between `old_root` (over `old_size`
+/// leaves) and `new_root` (over `new_size` leaves).
+pub fn verify_consistency(
+    old_size: usize,
+    new_size: usize,
+    old_root: &str,
+    new_root: &str,
+    proof: &[String],
+) -> Result<bool> {
+    if old_size > new_size {
+        return Ok(false);
+    }
+    if old_size == new_size {
+        return Ok(proof.is_empty() && old_root == new_root);
+    }
+    if old_size == 0 {
+        // The empty tree is a prefix of every tree, but only its own root
+        // stands for it.
+        return Ok(proof.is_empty() && old_root == hex::encode(blake3::hash(&[]).as_bytes()));
+    }
+    let mut nodes: Vec<Vec<u8>> = proof
+        .iter()
+        .map(|p| decode_hash(p))
+        .collect::<Result<_>>()?;
+    if old_size.is_power_of_two() {
+        nodes.insert(0, decode_hash(old_root)?);
+    }
+    let Some((first, rest)) = nodes.split_first() else {
+        return Ok(false);
+    };
+    let mut fn_ = old_size - 1;
+    let mut sn = new_size - 1;
+    while fn_ & 1 == 1 {
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    let mut fr: Hash = first
+        .as_slice()
+        .try_into()
+        .map_err(|_| anyhow!("bad proof node length"))?;
+    let mut sr = fr;
+    for c in rest {
+        if sn == 0 {
+            return Ok(false);
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            fr = node_hash(c, &fr);
+            sr = node_hash(c, &sr);
+            while fn_ & 1 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            sr = node_hash(&sr, c);
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    Ok(sn == 0 && hex::encode(fr) == old_root && hex::encode(sr) == new_root)
+}
+
+/// Right edge of an `rfc6962` tree.
+///
+/// Holds the roots of the perfect subtrees that cover the leaves appended so
+/// far. Appending and computing the root are
+/// O(log n), so callers can maintain a root without rehashing every leaf.
+#[derive(Clone, Debug, Default)]
+pub struct Frontier {
+    size: u64,
+    // Largest subtree first.
+    nodes: Vec<Hash>,
+}
+
+impl Frontier {
+    pub fn push(&mut self, leaf: &str) -> Result<()> {
+        let mut cur = leaf_hash(&decode_hash(leaf)?);
+        let mut size = self.size;
+        while size & 1 == 1 {
+            let left = self
+                .nodes
+                .pop()
+                .ok_or_else(|| anyhow!("frontier underflow"))?;
+            cur = node_hash(&left, &cur);
+            size >>= 1;
+        }
+        self.nodes.push(cur);
+        self.size += 1;
+        Ok(())
+    }
+
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Same value as `root(Alg::Rfc6962, leaves)` over the appended leaves.
+    pub fn root(&self) -> String {
+        let mut it = self.nodes.iter().rev();
+        let Some(last) = it.next() else {
+            return hex::encode(blake3::hash(&[]).as_bytes());
+        };
+        hex::encode(it.fold(*last, |acc, n| node_hash(n, &acc)))
+    }
+}
+
+fn sorted_parent(a_hex: &str, b_hex: &str) -> Result<String> {
+    let (a, b) = if a_hex <= b_hex {
+        (a_hex, b_hex)
+    } else {
+        (b_hex, a_hex)
+    };
+    let mut bytes = hex::decode(a).map_err(|e| anyhow!("bad leaf hex {a}: {e}"))?;
+    bytes.extend(hex::decode(b).map_err(|e| anyhow!("bad leaf hex {b}: {e}"))?);
+    Ok(hex::encode(blake3::hash(&bytes).as_bytes()))
+}
+
+fn leaf_hash(data: &[u8]) -> Hash {
+    let mut h = blake3::Hasher::new();
+    h.update(&[LEAF_PREFIX]);
+    h.update(data);
+    *h.finalize().as_bytes()
+}
+
+fn node_hash(left: &[u8], right: &[u8]) -> Hash {
+    let mut h = blake3::Hasher::new();
+    h.update(&[NODE_PREFIX]);
+    h.update(left);
+    h.update(right);
+    *h.finalize().as_bytes()
+}
+
+fn decode_hash(s: &str) -> Result<Vec<u8>> {
+    hex::decode(s).map_err(|e| anyhow!("bad leaf hex {s}: {e}"))
+}
+
+fn decode_leaves(leaves: &[String]) -> Result<Vec<Hash>> {
+    leaves
+        .iter()
+        .map(|l| Ok(leaf_hash(&decode_hash(l)?)))
+        .collect()
+}
+
+/// Largest power of two strictly smaller than `n` (n ≥ 2).
+const fn split_point(n: usize) -> usize {
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+/// MTH over already leaf-hashed inputs.
+fn mth(hashes: &[Hash]) -> Hash {
+    match hashes.len() {
+        0 => *blake3::hash(&[]).as_bytes(),
+        1 => hashes[0],
+        n => {
+            let k = split_point(n);
+            node_hash(&mth(&hashes[..k]), &mth(&hashes[k..]))
+        }
+    }
+}
+
+fn rfc_path(m: usize, hashes: &[Hash], out: &mut Vec<PathStep>) {
+    let n = hashes.len();
+    if n <= 1 {
+        return;
+    }
+    let k = split_point(n);
+    if m < k {
+        rfc_path(m, &hashes[..k], out);
+        out.push(PathStep::Positional {
+            side: Side::Right,
+            hash: hex::encode(mth(&hashes[k..])),
+        });
+    } else {
+        rfc_path(m - k, &hashes[k..], out);
+        out.push(PathStep::Positional {
+            side: Side::Left,
+            hash: hex::encode(mth(&hashes[..k])),
+        });
+    }
+}
+
+/// SUBPROOF(m, D[n], b) from RFC 6962 §2.1.2.
+fn subproof(m: usize, hashes: &[Hash], complete: bool, out: &mut Vec<Hash>) {
+    let n = hashes.len();
+    if m == n {
+        if !complete {
+            out.push(mth(hashes));
+        }
+        return;
+    }
+    let k = split_point(n);
+    if m <= k {
+        subproof(m, &hashes[..k], complete, out);
+        out.push(mth(&hashes[k..]));
+    } else {
+        subproof(m - k, &hashes[k..], false, out);
+        out.push(mth(&hashes[..k]));
+    }
+}
@@ -0,0 +1,337 @@
+#![allow(clippy::missing_errors_doc, clippy::must_use_candidate)]
+//! Ops receipts: Terraform plan/apply records emitted by `receipt emit`,
+//! completed by `receipt finalize`, signed by `sign` and anchored by `anchor`.
+//!
+//! `leaf` is the BLAKE3 of the canonical payload: the receipt without `leaf`,
+//! `merkle` and `sign.sig`, with object keys sorted. Receipts signed by older
+//! CLIs hashed `sign.sig` into the leaf too; those still verify as
+//! [`LeafForm::Legacy`]. `version` selects the form through
+//! [`crate::schema::VERSIONS`]; only receipts without one are matched by
+//! trying both.
+use crate::merkle::{self, PathStep};
+use crate::receipt::blake3_hex;
+use crate::schema::{self, Canon};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::{PublicKey, Signature, Verifier};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Approvals required before a node accepts an ops receipt.
+pub const MIN_APPROVALS: usize = 2;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Receipt {
+    /// Registered ops receipt version; absent on receipts emitted before
+    /// versioning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub id: String,
+    pub ts: String,
+    /// Leaf of the previous finalized receipt for the same `op.target`; absent on
+    /// the first receipt of a chain and on receipts that predate chaining.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev: Option<String>,
+    pub actor: Actor,
+    pub op: Op,
+    pub build: Build,
+    pub env: Env,
+    pub sign: Sign,
+    pub leaf: String,
+    pub merkle: Merkle,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Actor {
+    pub id: String,
+    pub cap: Vec<String>,
+    pub sig: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Op {
+    pub kind: String,
+    pub target: String,
+    #[serde(default)]
+    pub risk: Option<String>,
+    #[serde(default)]
+    pub change_window: Option<String>,
+    #[serde(default)]
+    pub approvals: Vec<String>,
+    pub plan_hash: String,
+    pub apply_hash: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Build {
+    pub repo: String,
+    pub commit: String,
+    pub binary_hash: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Env {
+    #[serde(default)]
+    pub ci: Option<String>,
+    #[serde(default)]
+    pub runner: Option<String>,
+    #[serde(default)]
+    pub tf_version: Option<String>,
+    #[serde(default)]
+    pub plugins: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub entries: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Sign {
+    pub alg: String,
+    #[serde(rename = "sig")]
+    pub signature: String,
+    #[serde(rename = "pub")]
+    pub public_key: String,
+}
+
+impl Sign {
+    pub fn none() -> Self {
+        Self {
+            alg: "none".into(),
+            signature: String::new(),
+            public_key: String::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Merkle {
+    /// Tree mode the path was computed with; absent on v0.1 receipts (sorted-pair).
+    #[serde(default)]
+    pub alg: merkle::Alg,
+    pub date: String,
+    pub path: Vec<PathStep>,
+    pub root: String,
+}
+
+/// Which canonical payload a receipt's `leaf` was computed over.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LeafForm {
+    /// Payload excludes `sign.sig`.
+    V2,
+    /// Payload includes `sign.sig` (older CLIs).
+    Legacy,
+}
+
+impl Receipt {
+    /// The leaf form the declared `version` uses; `None` for an unregistered
+    /// version, [`LeafForm::V2`] when no version is declared.
+    pub fn declared_form(&self) -> Option<LeafForm> {
+        let Some(v) = self.version.as_deref() else {
+            return Some(LeafForm::V2);
+        };
+        match schema::version("ops-receipt", v)?.canon {
+            Canon::Ops(form) => Some(form),
+            Canon::Artifact => None,
+        }
+    }
+
+    /// Leaf for the receipt's current contents under its declared version.
+    pub fn canonical_leaf(&self) -> Result<String> {
+        match self.declared_form() {
+            Some(LeafForm::V2) => Ok(canonical_leaf_hex(self)),
+            Some(LeafForm::Legacy) => Ok(canonical_leaf_hex_legacy(self)),
+            None => Err(anyhow!(
+                "unsupported ops-receipt version {}",
+                self.version.as_deref().unwrap_or_default()
+            )),
+        }
+    }
+
+    /// The form `leaf` matches, or `None` if the receipt was altered after
+    /// its leaf was computed.
+    pub fn leaf_form(&self) -> Option<LeafForm> {
+        if self.version.is_some() {
+            return match self.canonical_leaf() {
+                Ok(leaf) if leaf == self.leaf => self.declared_form(),
+                _ => None,
+            };
+        }
+        // Unversioned receipts: try both forms.
+        if canonical_leaf_hex(self) == self.leaf {
+            Some(LeafForm::V2)
+        } else if canonical_leaf_hex_legacy(self) == self.leaf {
+            Some(LeafForm::Legacy)
+        } else {
+            None
+        }
+    }
+
+    /// Check the ed25519 signature over the payload of the given form.
+    pub fn verify_signature(&self, form: LeafForm) -> Result<()> {
+        if self.sign.alg.to_lowercase() != "ed25519"
+            || self.sign.signature.is_empty()
+            || self.sign.public_key.is_empty()
+        {
+            return Err(anyhow!("missing ed25519 signature"));
+        }
+        let pub_bytes = general_purpose::STANDARD
+            .decode(self.sign.public_key.as_bytes())
+            .map_err(|e| anyhow!("bad public b64: {e}"))?;
+        let sig_bytes = general_purpose::STANDARD
+            .decode(self.sign.signature.as_bytes())
+            .map_err(|e| anyhow!("bad signature b64: {e}"))?;
+        let pk = PublicKey::from_bytes(&pub_bytes).map_err(|e| anyhow!("bad public: {e}"))?;
+        let sig = Signature::from_bytes(&sig_bytes).map_err(|e| anyhow!("bad signature: {e}"))?;
+        let msg = match form {
+            LeafForm::V2 => canonical_payload_json(self),
+            LeafForm::Legacy => canonical_payload_json_legacy(self),
+        };
+        pk.verify(msg.as_bytes(), &sig)
+            .map_err(|_| anyhow!("signature verification failed"))
+    }
+
+    /// Distinct, non-empty approvals.
+    pub fn approvals(&self) -> Vec<&str> {
+        let mut out: Vec<&str> = Vec::new();
+        for a in self.op.approvals.iter().map(|a| a.trim()) {
+            if !a.is_empty() && !out.contains(&a) {
+                out.push(a);
+            }
+        }
+        out
+    }
+
+    /// Checks a node runs before accepting the receipt: canonical leaf, valid
+    /// signature and at least [`MIN_APPROVALS`] distinct approvals.
+    pub fn verify_for_ingest(&self) -> Result<LeafForm> {
+        let form = self
+            .leaf_form()
+            .ok_or_else(|| anyhow!("leaf mismatch: receipt tampered or not canonical"))?;
+        self.verify_signature(form)?;
+        let approvals = self.approvals().len();
+        if approvals < MIN_APPROVALS {
+            return Err(anyhow!(
+                "need >={MIN_APPROVALS} distinct approvals (have {approvals})"
+            ));
+        }
+        Ok(form)
+    }
+}
+
+/// Order the finalized receipts of one target into their chain, oldest first.
+///
+/// Fails unless they form a single unbroken chain: one receipt
+/// without `prev`, every other `prev` naming a receipt in the set, no forks,
+/// no cycles and ids increasing along the chain. Leaves are taken as given;
+/// check them with [`Receipt::leaf_form`] first.
+pub fn chain(receipts: Vec<Receipt>) -> Result<Vec<Receipt>> {
+    let leaves: std::collections::HashSet<String> =
+        receipts.iter().map(|r| r.leaf.clone()).collect();
+    let mut by_prev: BTreeMap<Option<String>, Receipt> = BTreeMap::new();
+    for rec in receipts {
+        if let Some(other) = by_prev.get(&rec.prev) {
+            return Err(match &rec.prev {
+                Some(prev) => anyhow!("fork: {} and {} both follow {}", other.id, rec.id, prev),
+                None => anyhow!(
+                    "{} and {} both start a chain for {}",
+                    other.id,
+                    rec.id,
+                    rec.op.target
+                ),
+            });
+        }
+        by_prev.insert(rec.prev.clone(), rec);
+    }
+    let mut chain: Vec<Receipt> = Vec::new();
+    let mut next = None;
+    while let Some(rec) = by_prev.remove(&next) {
+        if let Some(last) = chain.last() {
+            if rec.id <= last.id {
+                return Err(anyhow!(
+                    "{} is linked after {} but is not newer",
+                    rec.id,
+                    last.id
+                ));
+            }
+        }
+        next = Some(rec.leaf.clone());
+        chain.push(rec);
+    }
+    if let Some((prev, rec)) = by_prev.into_iter().next() {
+        return Err(match prev {
+            None => anyhow!(
+                "{} has no prev but the chain for {} already started",
+                rec.id,
+                rec.op.target
+            ),
+            Some(prev) if leaves.contains(&prev) => {
+                anyhow!("{} links into a cycle at {}", rec.id, prev)
+            }
+            Some(prev) => anyhow!("{} links to missing predecessor {}", rec.id, prev),
+        });
+    }
+    Ok(chain)
+}
+
+fn remove_leaf_and_merkle(mut v: Value) -> Value {
+    if let Value::Object(ref mut m) = v {
+        m.remove("leaf");
+        m.remove("merkle");
+    }
+    v
+}
+
+fn remove_leaf_merkle_and_sig(mut v: Value) -> Value {
+    if let Value::Object(ref mut m) = v {
+        m.remove("leaf");
+        m.remove("merkle");
+        if let Some(Value::Object(ref mut s)) = m.get_mut("sign") {
+            s.remove("sig");
+        }
+    }
+    v
+}
+
+fn sort_json(v: Value) -> Value {
+    match v {
+        Value::Object(map) => {
+            let mut b = BTreeMap::new();
+            for (k, val) in map {
+                b.insert(k, sort_json(val));
+            }
+            Value::Object(b.into_iter().collect())
+        }
+        Value::Array(arr) => Value::Array(arr.into_iter().map(sort_json).collect()),
+        _ => v,
+    }
+}
+
+fn to_value<T: Serialize>(t: &T) -> Value {
+    serde_json::to_value(t).expect("serialize")
+}
+
+/// Signed payload: the receipt without `leaf`, `merkle` and `sign.sig`.
+///
+/// # Panics
+/// If `t` does not serialize to JSON, e.g. a map with non-string keys.
+pub fn canonical_payload_json<T: Serialize>(t: &T) -> String {
+    let v = sort_json(remove_leaf_merkle_and_sig(to_value(t)));
+    serde_json::to_string(&v).expect("serialize")
+}
+
+/// Payload as older CLIs computed it, including `sign.sig`.
+///
+/// # Panics
+/// As [`canonical_payload_json`].
+pub fn canonical_payload_json_legacy<T: Serialize>(t: &T) -> String {
+    let v = sort_json(remove_leaf_and_merkle(to_value(t)));
+    serde_json::to_string(&v).expect("serialize")
+}
+
+pub fn canonical_leaf_hex<T: Serialize>(t: &T) -> String {
+    blake3_hex(canonical_payload_json(t).as_bytes())
+}
+
+pub fn canonical_leaf_hex_legacy<T: Serialize>(t: &T) -> String {
+    blake3_hex(canonical_payload_json_legacy(t).as_bytes())
+}
@@ -16,6 +16,9 @@ use std::path::Path;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Receipt {
+    /// Registered receipt version; absent on receipts built before versioning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
     pub actor: Actor,
     pub env: BTreeMap<String, String>,
     pub ts: String,
@@ -57,6 +60,7 @@ pub fn build_receipt(subject: Subject) -> Result<Receipt> {
     let env = collect_env_metadata().entries;
     let ts = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
     Ok(Receipt {
+        version: Some(crate::schema::RECEIPT_VERSION.to_string()),
         actor,
         env,
         ts,
@@ -197,8 +201,9 @@ pub fn build_provenance(
     }
 }
 
+/// RFC 8785 (JCS) bytes of `v`; the form hashed for ledger digests.
 pub fn canonical_json_bytes<T: Serialize>(v: &T) -> Vec<u8> {
-    serde_json::to_vec(v).expect("serialize")
+    crate::jcs::to_vec_from(v).expect("serialize")
 }
 
 #[must_use]
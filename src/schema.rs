@@ -1,34 +1,85 @@
 #![allow(clippy::missing_errors_doc, clippy::explicit_auto_deref, clippy::non_std_lazy_statics)]
-use anyhow::{anyhow, Result};
-use jsonschema::{Draft, JSONSchema};
+use crate::ops::LeafForm;
+use jsonschema::JSONSchema;
+use serde::Serialize;
 use serde_json::json;
 use serde_json::Value;
+use std::sync::LazyLock;
 
-// Minimal schemas to guard structure; refine over time.
-pub static RECEIPT_SCHEMA: std::sync::LazyLock<Value> = std::sync::LazyLock::new(|| {
+// Shared constraints. Digests are lowercase hex BLAKE3; keys and signatures
+// are standard base64.
+const HEX_DIGEST: &str = "^[0-9a-f]{64}$";
+const DID: &str = "^did:[a-z0-9]+:(?:[A-Za-z0-9._:-]|%[0-9A-Fa-f]{2})*(?:[A-Za-z0-9._-]|%[0-9A-Fa-f]{2})$";
+/// Actor `receipt emit` wrote before receipts carried a `version`, when no
+/// DID resolved. Accepted on unversioned ops receipts only.
+const LEGACY_ACTOR: &str = "did:placeholder";
+const BASE64: &str = "^(?:[A-Za-z0-9+/]{4})*(?:[A-Za-z0-9+/]{2}==|[A-Za-z0-9+/]{3}=)?$";
+
+fn digest() -> Value {
+    json!({"type": "string", "pattern": HEX_DIGEST})
+}
+
+/// Root of a tree over `count` leaves: a digest, or `""` for an empty
+/// `sorted-pair` tree.
+fn tree_root(count: &str) -> Value {
+    json!({
+        "if": {"required": [count], "properties": {count: {"const": 0}}},
+        "then": {"properties": {"root": {"type": "string", "pattern": "^(?:[0-9a-f]{64})?$"}}},
+        "else": {"properties": {"root": digest()}}
+    })
+}
+
+fn actor() -> Value {
+    json!({"type": "object", "required": ["id"], "properties": {"id": {"type": "string", "pattern": DID}}})
+}
+
+fn base64() -> Value {
+    json!({"type": "string", "pattern": BASE64})
+}
+
+pub static RECEIPT_SCHEMA: LazyLock<Value> = LazyLock::new(|| {
     json!({
         "$schema": "https://json-schema.org/draft/2020-12/schema",
         "$id": "https://vaultmesh.dev/schema/receipt.json",
         "type": "object",
         "required": ["actor", "env", "ts", "subject"],
         "properties": {
-            "actor": {"type": "object", "required": ["id"], "properties": {"id": {"type":"string"}}},
+            "version": {"enum": ["0.1"]},
+            "actor": actor(),
             "env": {"type": "object"},
             "ts": {"type": "string", "format": "date-time"},
             "subject": {
                 "type": "object",
                 "required": ["kind", "digest"],
-                "properties": {"kind": {"type":"string"}, "digest": {"type":"string"}}
+                "properties": {"kind": {"type":"string"}, "digest": digest()}
+            },
+            "sign": {
+                "oneOf": [
+                    {"type": "null"},
+                    {
+                        "type": "object",
+                        "required": ["pub", "sig"],
+                        "properties": {"alg": {"type": "string"}, "pub": base64(), "sig": base64()}
+                    }
+                ]
             },
-            "sign": {"type": ["object", "null"]},
             "provenance": {"type": ["object", "null"]},
-            "provenance_ref": {"type": ["object", "null"]}
+            "provenance_ref": {
+                "oneOf": [
+                    {"type": "null"},
+                    {
+                        "type": "object",
+                        "required": ["path", "digest"],
+                        "properties": {"path": {"type": "string"}, "digest": digest()}
+                    }
+                ]
+            }
         },
         "additionalProperties": true
     })
 });
 
-pub static PROVENANCE_SCHEMA: std::sync::LazyLock<Value> = std::sync::LazyLock::new(|| {
+pub static PROVENANCE_SCHEMA: LazyLock<Value> = LazyLock::new(|| {
     json!({
         "$schema": "https://json-schema.org/draft/2020-12/schema",
         "$id": "https://vaultmesh.dev/schema/provenance.json",
@@ -36,8 +87,8 @@ pub static PROVENANCE_SCHEMA: std::sync::LazyLock<Value> = std::sync::LazyLock::
         "required": ["artifact", "artifact_hash", "actor", "build", "ci", "ts"],
         "properties": {
             "artifact": {"type": "string"},
-            "artifact_hash": {"type": "string"},
-            "actor": {"type": "object", "required": ["id"], "properties": {"id": {"type":"string"}}},
+            "artifact_hash": digest(),
+            "actor": actor(),
             "build": {"type": "object"},
             "ci": {"type": "object"},
             "ts": {"type": "object", "required": ["built"], "properties": {"built": {"type":"string", "format":"date-time"}}}
@@ -46,30 +97,299 @@ pub static PROVENANCE_SCHEMA: std::sync::LazyLock<Value> = std::sync::LazyLock::
     })
 });
 
-pub fn validate_receipt(v: &Value) -> Result<()> {
-    let compiled = JSONSchema::options().with_draft(Draft::Draft7).compile(&*RECEIPT_SCHEMA)
-        .map_err(|e| anyhow!("invalid receipt schema: {e}"))?;
-    if let Err(errs) = compiled.validate(v) {
-        let mut msgs = Vec::new();
-        for e in errs {
-            msgs.push(e.to_string());
-        }
-        return Err(anyhow!("receipt schema violation: {}", msgs.join("; ")));
+pub static OPS_RECEIPT_SCHEMA: LazyLock<Value> = LazyLock::new(|| {
+    // Empty until the receipt is finalized / anchored.
+    let pending = json!({"type": "string", "pattern": "^(?:[0-9a-f]{64})?$"});
+    let opt_string = json!({"type": ["string", "null"]});
+    // `sign` is all-empty on unsigned receipts.
+    let opt_base64 = json!({"anyOf": [{"const": ""}, base64()]});
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": "https://vaultmesh.dev/schema/ops-receipt.json",
+        "title": "Ops receipt (receipt emit|finalize, sign, anchor)",
+        "type": "object",
+        "required": ["id", "ts", "actor", "op", "build", "env", "sign", "leaf", "merkle"],
+        "if": {"required": ["version"]},
+        "then": {"properties": {"actor": {"properties": {"id": {"pattern": DID}}}}},
+        "properties": {
+            "version": {"enum": ["0.1", "0.2"]},
+            "id": {"type": "string"},
+            "ts": {"type": "string", "format": "date-time"},
+            "prev": digest(),
+            "actor": {
+                "type": "object",
+                "required": ["id", "cap", "sig"],
+                "properties": {
+                    "id": {"type": "string", "anyOf": [{"pattern": DID}, {"const": LEGACY_ACTOR}]},
+                    "cap": {"type": "array", "items": {"type": "string"}},
+                    "sig": {"type": "string"}
+                }
+            },
+            "op": {
+                "type": "object",
+                "required": ["kind", "target", "plan_hash", "apply_hash"],
+                "properties": {
+                    "kind": {"type": "string"},
+                    "target": {"type": "string"},
+                    "risk": opt_string,
+                    "change_window": opt_string,
+                    "approvals": {"type": "array", "items": {"type": "string"}},
+                    "plan_hash": digest(),
+                    "apply_hash": pending
+                }
+            },
+            "build": {
+                "type": "object",
+                "required": ["repo", "commit", "binary_hash"],
+                "properties": {
+                    "repo": {"type": "string"},
+                    "commit": {"type": "string"},
+                    "binary_hash": {"type": "string"}
+                }
+            },
+            "env": {"type": "object"},
+            "sign": {
+                "type": "object",
+                "required": ["alg", "sig", "pub"],
+                "properties": {
+                    "alg": {"type": "string"},
+                    "sig": opt_base64,
+                    "pub": opt_base64
+                }
+            },
+            "leaf": digest(),
+            "merkle": {
+                "type": "object",
+                "required": ["date", "path", "root"],
+                "properties": {
+                    "alg": {"enum": ["sorted-pair", "rfc6962"]},
+                    "date": {"type": "string"},
+                    "path": {"type": "array", "items": {"$ref": "#/$defs/path_step"}},
+                    "root": pending
+                }
+            }
+        },
+        "$defs": {
+            "path_step": {
+                "oneOf": [
+                    digest(),
+                    {
+                        "type": "object",
+                        "required": ["side", "hash"],
+                        "properties": {"side": {"enum": ["left", "right"]}, "hash": digest()},
+                        "additionalProperties": false
+                    }
+                ]
+            }
+        },
+        "additionalProperties": true
+    })
+});
+
+pub static ROOT_SCHEMA: LazyLock<Value> = LazyLock::new(|| {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": "https://vaultmesh.dev/schema/root.json",
+        "title": "Sealed root document (seal)",
+        "type": "object",
+        "required": ["date", "root", "count"],
+        "allOf": [tree_root("count")],
+        "properties": {
+            "date": {"type": "string", "format": "date"},
+            "root": {"type": "string"},
+            "count": {"type": "integer", "minimum": 0},
+            "alg": {"enum": ["sorted-pair", "rfc6962"]},
+            "sth": {
+                "type": "object",
+                "required": ["alg", "size", "root", "timestamp", "node", "sig"],
+                "allOf": [tree_root("size")],
+                "properties": {
+                    "alg": {"enum": ["sorted-pair", "rfc6962"]},
+                    "size": {"type": "integer", "minimum": 0},
+                    "root": {"type": "string"},
+                    "timestamp": {"type": "string", "format": "date-time"},
+                    "node": {"type": "string", "pattern": DID},
+                    "sig": base64()
+                }
+            }
+        },
+        "additionalProperties": true
+    })
+});
+
+/// Version written into new artifact receipts.
+pub const RECEIPT_VERSION: &str = "0.1";
+/// Version written into new ops receipts.
+pub const OPS_RECEIPT_VERSION: &str = "0.2";
+
+/// How a document version is canonicalized before hashing and signing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Canon {
+    /// Ops receipt `leaf`: sorted JSON without `leaf` and `merkle`; the form
+    /// says whether `sign.sig` is also dropped.
+    Ops(LeafForm),
+    /// Artifact receipt signature: canonical hash of the receipt without `sign`.
+    Artifact,
+}
+
+/// One registered document version.
+#[derive(Debug)]
+pub struct Version {
+    /// Schema name, as in [`NAMES`].
+    pub kind: &'static str,
+    pub version: &'static str,
+    pub schema: &'static LazyLock<Value>,
+    pub canon: Canon,
+    validator: &'static LazyLock<JSONSchema>,
+}
+
+/// Every version a node accepts. Documents that predate the `version` field
+/// validate against the current schema of their kind.
+pub static VERSIONS: &[Version] = &[
+    Version {
+        kind: "receipt",
+        version: "0.1",
+        schema: &RECEIPT_SCHEMA,
+        canon: Canon::Artifact,
+        validator: &RECEIPT_VALIDATOR,
+    },
+    Version {
+        kind: "ops-receipt",
+        version: "0.1",
+        schema: &OPS_RECEIPT_SCHEMA,
+        canon: Canon::Ops(LeafForm::Legacy),
+        validator: &OPS_RECEIPT_VALIDATOR,
+    },
+    Version {
+        kind: "ops-receipt",
+        version: "0.2",
+        schema: &OPS_RECEIPT_SCHEMA,
+        canon: Canon::Ops(LeafForm::V2),
+        validator: &OPS_RECEIPT_VALIDATOR,
+    },
+];
+
+#[must_use]
+pub fn version(kind: &str, version: &str) -> Option<&'static Version> {
+    VERSIONS.iter().find(|v| v.kind == kind && v.version == version)
+}
+
+/// The registry entry `doc` declares in its `version` field, or `None` when
+/// it has none.
+pub fn declared(kind: &str, doc: &Value) -> Result<Option<&'static Version>, SchemaError> {
+    let unsupported = |message: String| SchemaError {
+        what: kind.to_string(),
+        violations: vec![Violation {
+            instance_path: "/version".into(),
+            schema_path: "/properties/version".into(),
+            keyword: "version".into(),
+            message,
+        }],
+    };
+    match doc.get("version") {
+        None => Ok(None),
+        Some(Value::String(v)) => version(kind, v)
+            .map(Some)
+            .ok_or_else(|| unsupported(format!("unsupported {kind} version {v}"))),
+        Some(other) => Err(unsupported(format!("{kind} version must be a string, got {other}"))),
     }
-    Ok(())
-}
-
-pub fn validate_provenance(v: &Value) -> Result<()> {
-    let compiled = JSONSchema::options().with_draft(Draft::Draft7).compile(&*PROVENANCE_SCHEMA)
-        .map_err(|e| anyhow!("invalid provenance schema: {e}"))?;
-    if let Err(errs) = compiled.validate(v) {
-        let mut msgs = Vec::new();
-        for e in errs {
-            msgs.push(e.to_string());
-        }
-        return Err(anyhow!("provenance schema violation: {}", msgs.join("; ")));
+}
+
+/// Published schemas by name, for `vaultmesh schema print`.
+pub const NAMES: &[&str] = &["receipt", "provenance", "ops-receipt", "root"];
+
+#[must_use]
+pub fn by_name(name: &str) -> Option<&'static Value> {
+    match name {
+        "receipt" => Some(&*RECEIPT_SCHEMA),
+        "provenance" => Some(&*PROVENANCE_SCHEMA),
+        "ops-receipt" => Some(&*OPS_RECEIPT_SCHEMA),
+        "root" => Some(&*ROOT_SCHEMA),
+        _ => None,
     }
-    Ok(())
 }
 
+/// One failed schema check.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    /// JSON Pointer to the offending value in the document ("" for the root).
+    pub instance_path: String,
+    /// JSON Pointer to the failing keyword in the schema.
+    pub schema_path: String,
+    /// The failing keyword, e.g. `required` or `format`.
+    pub keyword: String,
+    pub message: String,
+}
+
+/// A document that does not match its schema.
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("{what} schema violation: {}", messages(.violations))]
+pub struct SchemaError {
+    /// Document kind, e.g. `receipt` or `ops receipt`.
+    pub what: String,
+    pub violations: Vec<Violation>,
+}
+
+fn messages(violations: &[Violation]) -> String {
+    violations.iter().map(|v| v.message.as_str()).collect::<Vec<_>>().join("; ")
+}
+
+// Schemas are compiled once, on first use, under the draft their `$schema`
+// names. 2020-12 treats `format` as an annotation by default; we assert it.
+fn compile(schema: &Value) -> JSONSchema {
+    JSONSchema::options()
+        .should_validate_formats(true)
+        .compile(schema)
+        .expect("published schemas compile")
+}
+
+static RECEIPT_VALIDATOR: LazyLock<JSONSchema> = LazyLock::new(|| compile(&RECEIPT_SCHEMA));
+static PROVENANCE_VALIDATOR: LazyLock<JSONSchema> = LazyLock::new(|| compile(&PROVENANCE_SCHEMA));
+static OPS_RECEIPT_VALIDATOR: LazyLock<JSONSchema> = LazyLock::new(|| compile(&OPS_RECEIPT_SCHEMA));
+static ROOT_VALIDATOR: LazyLock<JSONSchema> = LazyLock::new(|| compile(&ROOT_SCHEMA));
+
+fn validate(what: &str, validator: &JSONSchema, v: &Value) -> Result<(), SchemaError> {
+    let Err(errs) = validator.validate(v) else {
+        return Ok(());
+    };
+    let violations = errs
+        .map(|e| {
+            let schema_path = e.schema_path.to_string();
+            Violation {
+                instance_path: e.instance_path.to_string(),
+                keyword: schema_path.rsplit('/').next().unwrap_or_default().to_string(),
+                schema_path,
+                message: e.to_string(),
+            }
+        })
+        .collect();
+    Err(SchemaError { what: what.to_string(), violations })
+}
+
+fn validate_versioned(
+    what: &str,
+    kind: &str,
+    current: &JSONSchema,
+    v: &Value,
+) -> Result<(), SchemaError> {
+    let validator = declared(kind, v)
+        .map_err(|e| SchemaError { what: what.to_string(), ..e })?
+        .map_or(current, |ver| &**ver.validator);
+    validate(what, validator, v)
+}
 
+pub fn validate_receipt(v: &Value) -> Result<(), SchemaError> {
+    validate_versioned("receipt", "receipt", &RECEIPT_VALIDATOR, v)
+}
+
+pub fn validate_provenance(v: &Value) -> Result<(), SchemaError> {
+    validate("provenance", &PROVENANCE_VALIDATOR, v)
+}
+
+pub fn validate_ops_receipt(v: &Value) -> Result<(), SchemaError> {
+    validate_versioned("ops receipt", "ops-receipt", &OPS_RECEIPT_VALIDATOR, v)
+}
+
+pub fn validate_root(v: &Value) -> Result<(), SchemaError> {
+    validate("root", &ROOT_VALIDATOR, v)
+}
@@ -0,0 +1,60 @@
+#![allow(clippy::missing_errors_doc, clippy::must_use_candidate)]
+//! Signed tree heads: a node's signed claim about its ledger root.
+use crate::identity::{did_key_from_public, public_from_did_key};
+use crate::merkle::Alg;
+use crate::receipt::hash_canonical;
+use anyhow::{anyhow, Result};
+use base64::Engine as _;
+use ed25519_dalek::{Keypair, Signature, Signer, Verifier};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SignedTreeHead {
+    pub alg: Alg,
+    pub size: u64,
+    pub root: String,
+    /// RFC3339 time the head was signed.
+    pub timestamp: String,
+    /// `did:key` of the signing node.
+    pub node: String,
+    /// Base64 ed25519 signature over the canonical hash of the other fields.
+    pub sig: String,
+}
+
+/// Sign `(alg, size, root)` with `kp`, stamping the current time.
+pub fn sign(alg: Alg, size: u64, root: &str, kp: &Keypair) -> Result<SignedTreeHead> {
+    let mut sth = SignedTreeHead {
+        alg,
+        size,
+        root: root.to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        node: did_key_from_public(kp.public.as_bytes()),
+        sig: String::new(),
+    };
+    let sig: Signature = kp.sign(signing_digest(&sth)?.as_bytes());
+    sth.sig = base64::engine::general_purpose::STANDARD.encode(sig.to_bytes());
+    Ok(sth)
+}
+
+impl SignedTreeHead {
+    /// Check the signature against the key embedded in `node`.
+    pub fn verify(&self) -> Result<()> {
+        let pk = public_from_did_key(&self.node)?;
+        let sig_bytes = base64::engine::general_purpose::STANDARD
+            .decode(self.sig.as_bytes())
+            .map_err(|e| anyhow!("bad sth signature b64: {e}"))?;
+        let sig =
+            Signature::from_bytes(&sig_bytes).map_err(|e| anyhow!("bad sth signature: {e}"))?;
+        pk.verify(signing_digest(self)?.as_bytes(), &sig)
+            .map_err(|e| anyhow!("sth signature verify failed: {e}"))
+    }
+}
+
+fn signing_digest(sth: &SignedTreeHead) -> Result<String> {
+    let mut v = serde_json::to_value(sth)?;
+    if let Value::Object(ref mut m) = v {
+        m.remove("sig");
+    }
+    Ok(hash_canonical(&v))
+}
@@ -0,0 +1,241 @@
+#![allow(clippy::missing_errors_doc, clippy::must_use_candidate)]
+//! Background sync for `vaultmesh gateway --sync-interval`.
+//!
+//! Every interval the gateway pulls ([`super::pull::pull_peer`]) from each
+//! registered `full` and `read-only` peer that is due. A failed pull backs the
+//! peer off exponentially (twice the interval, then four times, … up to
+//! [`MAX_BACKOFF`] or the interval, whichever is longer); a successful one
+//! resets it. Attempt results are kept in memory and served, together with
+//! each peer's persisted pull cursor, by `GET /v1/peers` ([`health`]). Each
+//! attempt also records the size of the peer's tree head, so a peer's lag is
+//! reported in leaves as well as in time. A round that fails as a whole (the
+//! registry cannot be read, or the round's task dies) is recorded there too
+//! rather than printed.
+use super::{peers, pull, PeerInfo, TrustLevel};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Longest wait between attempts for a failing peer, unless the interval is longer.
+pub const MAX_BACKOFF: Duration = Duration::from_hours(1);
+
+/// Outcome of the latest attempts for one peer, keyed by peer id in [`STATUS`].
+#[derive(Debug, Clone, Default)]
+struct Attempts {
+    last_attempt: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    /// Consecutive failed attempts.
+    failures: u32,
+    next_attempt: Option<DateTime<Utc>>,
+    /// Objects the last successful pull fetched.
+    fetched: usize,
+    /// Tree size in the peer's head as last fetched, even if the pull failed.
+    head_size: Option<u64>,
+}
+
+static STATUS: Mutex<Option<HashMap<String, Attempts>>> = Mutex::new(None);
+
+/// Failure of a whole sync round, as opposed to one peer's pull.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RoundError {
+    pub at: String,
+    pub error: String,
+}
+
+static ROUND_ERROR: Mutex<Option<RoundError>> = Mutex::new(None);
+
+/// Sync health of the gateway, as served by `GET /v1/peers`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SyncHealth {
+    /// Error of the last round, if it failed as a whole; cleared by the next
+    /// round that completes.
+    pub last_error: Option<RoundError>,
+    /// Every registered peer, in registry order.
+    pub peers: Vec<PeerHealth>,
+}
+
+/// Sync health of one registered peer, as served by `GET /v1/peers`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PeerHealth {
+    pub id: String,
+    pub url: String,
+    pub trust: TrustLevel,
+    /// Start of the last successful pull (from the peer's cursor).
+    pub last_sync: Option<String>,
+    /// Seconds since `last_sync`.
+    pub lag_secs: Option<i64>,
+    /// Peer tree size and cursor as of `last_sync`.
+    pub size: Option<u64>,
+    pub cursor: Option<u64>,
+    /// Peer tree size in the tree head this gateway last fetched from it.
+    pub head_size: Option<u64>,
+    /// Leaves of that tree head beyond `size`, i.e. not yet mirrored.
+    pub behind: Option<u64>,
+    /// Objects the last successful pull by this gateway fetched.
+    pub fetched: usize,
+    pub last_attempt: Option<String>,
+    /// Error of the last attempt, if it failed.
+    pub last_error: Option<String>,
+    /// Consecutive failed attempts.
+    pub failures: u32,
+    /// Earliest time the next attempt runs, while backing off.
+    pub next_attempt: Option<String>,
+}
+
+fn rfc3339(t: DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+/// `60s`, `5m`, `1h` or a plain number of seconds.
+pub fn parse_interval(s: &str) -> Result<Duration> {
+    let bad = || anyhow!("bad interval: {s} (expected e.g. 60s, 5m or 1h)");
+    let (n, scale) = match s.strip_suffix(['s', 'm', 'h']) {
+        Some(n) if s.ends_with('h') => (n, 3600),
+        Some(n) if s.ends_with('m') => (n, 60),
+        Some(n) => (n, 1),
+        None => (s, 1),
+    };
+    let secs = n.parse::<u64>().map_err(|_| bad())? * scale;
+    if secs == 0 {
+        return Err(bad());
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+/// Wait before retrying a peer that has failed `failures` times in a row.
+pub fn backoff(interval: Duration, failures: u32) -> Duration {
+    let cap = interval.max(MAX_BACKOFF);
+    interval
+        .checked_mul(1 << failures.min(16))
+        .map_or(cap, |d| d.min(cap))
+}
+
+fn with_status<T>(f: impl FnOnce(&mut HashMap<String, Attempts>) -> T) -> T {
+    let mut guard = STATUS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    f(guard.get_or_insert_with(HashMap::new))
+}
+
+/// Peers the daemon pulls from.
+const fn syncable(peer: &PeerInfo) -> bool {
+    matches!(peer.trust, TrustLevel::Full | TrustLevel::ReadOnly)
+}
+
+/// Pull once from every syncable peer whose backoff has expired. Failures are
+/// recorded per peer, not returned; only reading the registry can fail.
+pub fn sync_due(interval: Duration) -> Result<()> {
+    for peer in peers::load()?.into_iter().filter(syncable) {
+        let now = Utc::now();
+        let due = with_status(|s| {
+            s.get(&peer.id)
+                .and_then(|a| a.next_attempt)
+                .is_none_or(|t| t <= now)
+        });
+        if !due {
+            continue;
+        }
+        let base = pull::api_base(&peer.url);
+        let head = pull::head(&base);
+        let head_size = head
+            .as_ref()
+            .ok()
+            .filter(|h| h.node == peer.id)
+            .map(|h| h.size);
+        let result = head.and_then(|h| pull::pull_head(&base, &h, Some(&peer.id)));
+        with_status(|s| {
+            let a = s.entry(peer.id.clone()).or_default();
+            a.last_attempt = Some(now);
+            a.head_size = head_size.or(a.head_size);
+            match result {
+                Ok(report) => {
+                    a.last_error = None;
+                    a.failures = 0;
+                    a.next_attempt = None;
+                    a.fetched = report.fetched;
+                }
+                Err(e) => {
+                    a.last_error = Some(format!("{e:#}"));
+                    a.failures += 1;
+                    let wait = chrono::Duration::from_std(backoff(interval, a.failures))
+                        .unwrap_or(chrono::Duration::MAX);
+                    a.next_attempt = now.checked_add_signed(wait);
+                }
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Record the outcome of a round for [`health`].
+pub fn record_round(result: Result<()>) {
+    let error = result.err().map(|e| RoundError {
+        at: rfc3339(Utc::now()),
+        error: format!("{e:#}"),
+    });
+    *ROUND_ERROR
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = error;
+}
+
+/// Run [`sync_due`] every `interval` until the task is dropped.
+pub async fn run(interval: Duration) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        let result = tokio::task::spawn_blocking(move || sync_due(interval))
+            .await
+            .unwrap_or_else(|e| Err(anyhow!("sync task failed: {e}")));
+        record_round(result);
+    }
+}
+
+/// Sync health of the gateway and of every registered peer.
+pub fn health() -> Result<SyncHealth> {
+    let last_error = ROUND_ERROR
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone();
+    Ok(SyncHealth {
+        last_error,
+        peers: peer_health()?,
+    })
+}
+
+fn peer_health() -> Result<Vec<PeerHealth>> {
+    let now = Utc::now();
+    peers::load()?
+        .into_iter()
+        .map(|peer| {
+            let cursor = pull::cursor(&peer.id)?;
+            let attempts = with_status(|s| s.get(&peer.id).cloned()).unwrap_or_default();
+            let lag_secs = cursor
+                .as_ref()
+                .and_then(|c| DateTime::parse_from_rfc3339(&c.ts).ok())
+                .map(|t| (now - t.with_timezone(&Utc)).num_seconds());
+            let size = cursor.as_ref().map(|c| c.size);
+            Ok(PeerHealth {
+                last_sync: cursor.as_ref().map(|c| c.ts.clone()),
+                lag_secs,
+                size,
+                cursor: cursor.as_ref().map(|c| c.next),
+                head_size: attempts.head_size,
+                behind: attempts
+                    .head_size
+                    .map(|h| h.saturating_sub(size.unwrap_or(0))),
+                fetched: attempts.fetched,
+                last_attempt: attempts.last_attempt.map(rfc3339),
+                last_error: attempts.last_error,
+                failures: attempts.failures,
+                next_attempt: attempts.next_attempt.map(rfc3339),
+                id: peer.id,
+                url: peer.url,
+                trust: peer.trust,
+            })
+        })
+        .collect()
+}
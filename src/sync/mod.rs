@@ -1,15 +1,48 @@
-use blake3::Hasher;
+pub mod peers;
+pub mod daemon;
+pub mod pull;
+pub mod reconcile;
 
-#[derive(serde::Serialize, serde::Deserialize, Clone)]
+/// How much a node trusts a registered peer's pushes.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
 pub enum TrustLevel {
+    /// Pushes are verified and ingested into the ledger.
     Full,
+    /// May fetch, may not push.
     ReadOnly,
+    /// Pushes are verified and kept in the staging area, out of the ledger tree.
     Quarantine,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Clone)]
+impl std::str::FromStr for TrustLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "full" => Ok(Self::Full),
+            "read-only" => Ok(Self::ReadOnly),
+            "quarantine" => Ok(Self::Quarantine),
+            other => Err(anyhow::anyhow!(
+                "unknown trust level: {other} (expected full|read-only|quarantine)"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for TrustLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Full => "full",
+            Self::ReadOnly => "read-only",
+            Self::Quarantine => "quarantine",
+        })
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct PeerInfo {
-    pub id: String,  // did:web / did:key
+    pub id: String,  // did:key of the peer's actor key
     pub url: String, // https://peer/v1/ledger
     pub trust: TrustLevel,
 }
@@ -19,13 +52,3 @@ pub struct PeerReceiptBundle {
     pub receipt: crate::receipt::Receipt,
     pub provenance: crate::receipt::Provenance,
 }
-
-/// Extremely simple integrity fold over digests (upgradeable later).
-pub fn merkle_root(digests: &[String]) -> String {
-    let mut h = Hasher::new();
-    for d in digests {
-        h.update(d.as_bytes());
-    }
-    hex::encode(h.finalize().as_bytes())
-}
-
@@ -0,0 +1,90 @@
+#![allow(clippy::missing_errors_doc)]
+//! Persisted peer registry: `peers.jsonl` in the ledger dir.
+//!
+//! One [`PeerInfo`] per line. The gateway consults it on every push. Changes rewrite the file
+//! under the ledger dir lock ([`crate::ledger::lock`]), so concurrent `peer`
+//! commands cannot drop each other's edits.
+use super::{PeerInfo, TrustLevel};
+use anyhow::{anyhow, Result};
+use std::path::PathBuf;
+
+const FILE: &str = "peers.jsonl";
+
+fn path() -> Result<PathBuf> {
+    Ok(crate::ledger::ledger_dir()?.join(FILE))
+}
+
+/// Every registered peer, in the order they were added.
+pub fn load() -> Result<Vec<PeerInfo>> {
+    let path = path()?;
+    let data = match std::fs::read(&path) {
+        Ok(d) => d,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    data.split(|b| *b == b'\n')
+        .filter(|l| !l.is_empty())
+        .map(|l| serde_json::from_slice(l).map_err(|e| anyhow!("bad {FILE} line: {e}")))
+        .collect()
+}
+
+/// Apply `f` to the registry and rewrite it via a temp file and rename,
+/// holding the ledger dir lock from the read to the rename.
+fn update<T>(f: impl FnOnce(&mut Vec<PeerInfo>) -> Result<T>) -> Result<T> {
+    let _held = crate::ledger::lock::exclusive(&crate::ledger::ledger_dir()?)?;
+    let mut peers = load()?;
+    let out = f(&mut peers)?;
+    save(&peers)?;
+    Ok(out)
+}
+
+fn save(peers: &[PeerInfo]) -> Result<()> {
+    let path = path()?;
+    let mut out = Vec::new();
+    for p in peers {
+        out.extend(serde_json::to_vec(p)?);
+        out.push(b'\n');
+    }
+    let tmp = path.with_extension("jsonl.tmp");
+    std::fs::write(&tmp, out)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+pub fn get(id: &str) -> Result<Option<PeerInfo>> {
+    Ok(load()?.into_iter().find(|p| p.id == id))
+}
+
+pub fn add(peer: PeerInfo) -> Result<()> {
+    // Requests are authenticated by the `did:key` in their signature
+    // (`crate::httpsig`), so no other DID method could ever match.
+    crate::identity::public_from_did_key(&peer.id)
+        .map_err(|e| anyhow!("peer id must be an ed25519 did:key: {} ({e})", peer.id))?;
+    update(|peers| {
+        if peers.iter().any(|p| p.id == peer.id) {
+            return Err(anyhow!("peer {} is already registered", peer.id));
+        }
+        peers.push(peer);
+        Ok(())
+    })
+}
+
+pub fn set_trust(id: &str, trust: TrustLevel) -> Result<()> {
+    update(|peers| {
+        let peer = peers
+            .iter_mut()
+            .find(|p| p.id == id)
+            .ok_or_else(|| anyhow!("no peer {id}"))?;
+        peer.trust = trust;
+        Ok(())
+    })
+}
+
+/// Remove `id`; returns whether it was registered.
+pub fn remove(id: &str) -> Result<bool> {
+    update(|peers| {
+        let before = peers.len();
+        peers.retain(|p| p.id != id);
+        Ok(peers.len() != before)
+    })
+}
@@ -0,0 +1,289 @@
+#![allow(clippy::missing_errors_doc)]
+//! Incremental mirroring of a peer's ledger.
+//!
+//! A pull fetches the peer's signed tree head (`GET /v1/sth`), pages through
+//! its index from the last cursor (`GET /v1/ledger?since=<seq>`), downloads
+//! each object this node does not hold (`GET /v1/ledger/:digest`) and checks
+//! it the way a push would be checked before ingesting it. Progress is kept
+//! per peer DID in `cursors.jsonl` in the ledger dir, so a repeated pull only
+//! transfers entries the peer indexed since. The cursor also records the
+//! peer's tree head; the next pull only proceeds if the peer proves its new
+//! head extends that one (`GET /v1/consistency`), so a peer that rewrites its
+//! history under the same key is caught.
+//!
+//! The tree head's signer must be in the peer registry ([`super::peers`]), as
+//! a push's signer must be: `full` and `read-only` peers (which may not push,
+//! but may be pulled from) are ingested, `quarantine` peers are staged
+//! ([`crate::ledger::staging`]), unknown peers are refused, also when the
+//! registry is empty.
+use super::{peers, PeerInfo, TrustLevel};
+use crate::ledger::{self, Entry};
+use crate::sth::SignedTreeHead;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const FILE: &str = "cursors.jsonl";
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+/// How far this node has mirrored one peer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    /// `did:key` the peer signs tree heads with.
+    pub peer: String,
+    /// Base URL last pulled from.
+    pub url: String,
+    /// First peer index `seq` not yet pulled.
+    pub next: u64,
+    /// Tree size and root of the peer's head at the last pull.
+    pub size: u64,
+    pub root: String,
+    pub ts: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PullReport {
+    pub peer: String,
+    /// Objects downloaded and ingested (or staged).
+    pub fetched: usize,
+    /// Listed objects this node already held.
+    pub present: usize,
+    /// Listed objects that failed verification, with the reason.
+    pub rejected: Vec<(String, String)>,
+    /// Whether objects went to the staging area rather than the ledger.
+    pub staged: bool,
+    pub cursor: Cursor,
+}
+
+#[derive(Deserialize)]
+struct Page {
+    entries: Vec<Entry>,
+    next: u64,
+    more: bool,
+}
+
+fn path() -> Result<PathBuf> {
+    Ok(ledger::ledger_dir()?.join(FILE))
+}
+
+/// Every stored cursor.
+pub fn cursors() -> Result<Vec<Cursor>> {
+    let data = match std::fs::read(path()?) {
+        Ok(d) => d,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    data.split(|b| *b == b'\n')
+        .filter(|l| !l.is_empty())
+        .map(|l| serde_json::from_slice(l).map_err(|e| anyhow!("bad {FILE} line: {e}")))
+        .collect()
+}
+
+pub fn cursor(peer: &str) -> Result<Option<Cursor>> {
+    Ok(cursors()?.into_iter().find(|c| c.peer == peer))
+}
+
+/// Replace the cursor for `cursor.peer`, rewriting the file via a temp file
+/// under the ledger dir lock, so concurrent pulls keep each other's cursors.
+fn save(cursor: &Cursor) -> Result<()> {
+    let _held = ledger::lock::exclusive(&ledger::ledger_dir()?)?;
+    let mut all = cursors()?;
+    all.retain(|c| c.peer != cursor.peer);
+    all.push(cursor.clone());
+    let mut out = Vec::new();
+    for c in &all {
+        out.extend(serde_json::to_vec(c)?);
+        out.push(b'\n');
+    }
+    let path = path()?;
+    let tmp = path.with_extension("jsonl.tmp");
+    std::fs::write(&tmp, out)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+pub(super) fn fetch(url: &str) -> Result<String> {
+    Ok(ureq::get(url).timeout(TIMEOUT).call()?.into_string()?)
+}
+
+/// `base` with a trailing `/v1`, as `sync push` and `sync verify` accept it.
+#[must_use]
+pub fn api_base(base: &str) -> String {
+    let mut base = base.trim_end_matches('/').to_string();
+    if !base.ends_with("/v1") {
+        base.push_str("/v1");
+    }
+    base
+}
+
+/// Check a downloaded (or imported) object as the gateway checks a push of
+/// the same kind.
+pub(crate) fn verify_object(kind: &str, bytes: &[u8]) -> Result<()> {
+    let v: serde_json::Value = serde_json::from_slice(bytes)?;
+    match kind {
+        "receipt" => {
+            crate::schema::validate_receipt(&v)?;
+            crate::receipt::verify_receipt(&serde_json::from_value(v)?)
+        }
+        ledger::OPS => {
+            crate::schema::validate_ops_receipt(&v)?;
+            let rec: crate::ops::Receipt = serde_json::from_value(v)?;
+            rec.verify_for_ingest().map(|_| ())
+        }
+        "provenance" => Ok(crate::schema::validate_provenance(&v)?),
+        other => Err(anyhow!("unsupported kind {other}")),
+    }
+}
+
+/// Whether objects from `peer` go to the staging area (`true`) or the ledger,
+/// or an error if the registry refuses them.
+pub(super) fn admission(peer: &str) -> Result<bool> {
+    match peers::get(peer)?.map(|p| p.trust) {
+        Some(TrustLevel::Full | TrustLevel::ReadOnly) => Ok(false),
+        Some(TrustLevel::Quarantine) => Ok(true),
+        None => Err(anyhow!("unknown peer {peer}; register it with `vaultmesh peer add`")),
+    }
+}
+
+/// Download `entry` from the peer at `base` (an `/v1` URL), verify it and
+/// ingest it, or stage it on behalf of `stage_as`. Returns the reason when
+/// the object fails verification; network errors are errors.
+///
+/// The object is classified locally and checked as that kind; the kind,
+/// commit and ref the peer lists for it are ignored.
+pub(super) fn mirror(base: &str, entry: &Entry, stage_as: Option<&str>) -> Result<Option<String>> {
+    let body = fetch(&format!("{base}/ledger/{}", entry.digest))?;
+    let actual = crate::receipt::blake3_hex(body.as_bytes());
+    let kind = ledger::classify(body.as_bytes());
+    let checked = if actual == entry.digest {
+        verify_object(&kind, body.as_bytes())
+    } else {
+        Err(anyhow!("content hashes to {actual}"))
+    };
+    if let Err(e) = checked {
+        return Ok(Some(e.to_string()));
+    }
+    match stage_as {
+        Some(peer) => ledger::staging::stage(&kind, body.as_bytes(), peer)?,
+        None => ledger::add_classified(&kind, body.as_bytes())?,
+    };
+    Ok(None)
+}
+
+/// Fetch and verify the peer's signed tree head.
+pub(super) fn head(base: &str) -> Result<SignedTreeHead> {
+    let head: SignedTreeHead = serde_json::from_str(&fetch(&format!("{base}/sth"))?)?;
+    head.verify()?;
+    Ok(head)
+}
+
+#[derive(Deserialize)]
+struct Consistency {
+    proof: Vec<String>,
+}
+
+/// Refuse a tree head that does not extend the one recorded at the last
+/// pull: a different root at the same size, or a larger tree the peer
+/// cannot prove consistent with it (`GET /v1/consistency`).
+fn extends(base: &str, prev: &Cursor, head: &SignedTreeHead) -> Result<()> {
+    let (old, new) = (usize::try_from(prev.size)?, usize::try_from(head.size)?);
+    let proof = if old == 0 || old == new {
+        Vec::new()
+    } else {
+        let url = format!("{base}/consistency?old={old}&new={new}");
+        serde_json::from_str::<Consistency>(&fetch(&url)?)?.proof
+    };
+    if !crate::merkle::verify_consistency(old, new, &prev.root, &head.root, &proof)? {
+        return Err(anyhow!(
+            "peer {} forked: tree head {} ({new} leaves) does not extend {} ({old} leaves)",
+            head.node,
+            head.root,
+            prev.root
+        ));
+    }
+    Ok(())
+}
+
+/// Mirror the ledger served at `base` into the local ledger.
+///
+/// Network and protocol errors abort the pull without moving the cursor, as
+/// does a tree head that does not extend the one the cursor recorded.
+/// Objects that fail verification are reported and skipped; the cursor moves
+/// past them.
+pub fn pull(base: &str) -> Result<PullReport> {
+    pull_as(base, None)
+}
+
+/// [`pull`] from a registered peer, refusing a tree head signed by any DID
+/// other than `peer.id` before anything is fetched.
+pub fn pull_peer(peer: &PeerInfo) -> Result<PullReport> {
+    pull_as(&peer.url, Some(&peer.id))
+}
+
+fn pull_as(base: &str, expect: Option<&str>) -> Result<PullReport> {
+    let base = api_base(base);
+    let head = head(&base)?;
+    pull_head(&base, &head, expect)
+}
+
+/// [`pull_as`] from the peer at `base` (an `/v1` URL), given the tree head
+/// [`head`] fetched from it.
+pub(super) fn pull_head(base: &str, head: &SignedTreeHead, expect: Option<&str>) -> Result<PullReport> {
+    let base = base.to_string();
+    let peer = head.node.clone();
+    if let Some(expected) = expect.filter(|id| *id != peer) {
+        return Err(anyhow!("{base} signs as {peer}, not {expected}"));
+    }
+    let stage = admission(&peer)?;
+
+    let previous = cursor(&peer)?;
+    if let Some(prev) = &previous {
+        if head.size < prev.size {
+            return Err(anyhow!(
+                "peer {peer} tree shrank from {} to {} leaves",
+                prev.size,
+                head.size
+            ));
+        }
+        extends(&base, prev, head)?;
+    }
+
+    let mut report = PullReport {
+        peer: peer.clone(),
+        fetched: 0,
+        present: 0,
+        rejected: Vec::new(),
+        staged: stage,
+        cursor: Cursor {
+            peer: peer.clone(),
+            url: base.clone(),
+            next: previous.map_or(0, |c| c.next),
+            size: head.size,
+            root: head.root.clone(),
+            ts: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        },
+    };
+    loop {
+        let page: Page = serde_json::from_str(&fetch(&format!(
+            "{base}/ledger?since={}",
+            report.cursor.next
+        ))?)?;
+        for entry in page.entries {
+            if ledger::contains(&entry.digest)? {
+                report.present += 1;
+                continue;
+            }
+            match mirror(&base, &entry, stage.then_some(peer.as_str()))? {
+                Some(why) => report.rejected.push((entry.digest, why)),
+                None => report.fetched += 1,
+            }
+        }
+        report.cursor.next = report.cursor.next.max(page.next);
+        if !page.more {
+            break;
+        }
+    }
+    save(&report.cursor)?;
+    Ok(report)
+}
@@ -0,0 +1,213 @@
+#![allow(clippy::missing_errors_doc, clippy::must_use_candidate)]
+//! Anti-entropy: find the symmetric difference of two ledgers' digest sets
+//! without listing either in full.
+//!
+//! The live digests ([`ledger::sorted_by_digest`]) are split into ranges by
+//! hex prefix. Each [`Range`] carries the number of digests under its prefix
+//! and a fingerprint, the BLAKE3 of those digests concatenated in sorted
+//! order. Two nodes compare fingerprints starting at the empty prefix; only
+//! mismatching ranges are split into their 16 child prefixes for the next
+//! round, and a range small enough (at most [`LEAF_MAX`] digests) is answered
+//! with its entries. For `n` digests and a few differences that takes about
+//! `log16(n / LEAF_MAX) + 1` round trips (`GET /v1/ledger/ranges`).
+use super::pull;
+use crate::ledger::{self, Entry};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Ranges with at most this many digests are answered with their entries.
+pub const LEAF_MAX: usize = 32;
+
+/// Most prefixes one `GET /v1/ledger/ranges` request may ask for.
+pub const MAX_PREFIXES: usize = 256;
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Range {
+    pub prefix: String,
+    pub count: usize,
+    pub fingerprint: String,
+    /// Entries under `prefix`, sorted by digest, when `count <= LEAF_MAX`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entries: Option<Vec<Entry>>,
+}
+
+#[derive(Deserialize)]
+struct Ranges {
+    ranges: Vec<Range>,
+}
+
+/// Result of reconciling with one peer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diff {
+    /// Entries the peer holds and this node does not.
+    pub remote_only: Vec<Entry>,
+    /// Entries this node holds and the peer does not.
+    pub local_only: Vec<Entry>,
+    /// Range requests made.
+    pub rounds: usize,
+}
+
+fn check_prefix(prefix: &str) -> Result<()> {
+    if prefix.len() > 64 || !prefix.bytes().all(|b| HEX.contains(&b)) {
+        return Err(anyhow!("bad digest prefix {prefix:?}"));
+    }
+    Ok(())
+}
+
+/// `entries` sorted by digest, as [`ranges`] and [`diff_with`] expect.
+pub fn sorted(mut entries: Vec<Entry>) -> Vec<Entry> {
+    entries.sort_by(|a, b| a.digest.cmp(&b.digest));
+    entries
+}
+
+/// Entries of `sorted` whose digest starts with `prefix`.
+fn under<'a>(sorted: &'a [Entry], prefix: &str) -> &'a [Entry] {
+    let start = sorted.partition_point(|e| e.digest.as_str() < prefix);
+    let len = sorted[start..].partition_point(|e| e.digest.starts_with(prefix));
+    &sorted[start..start + len]
+}
+
+fn summarize(sorted: &[Entry], prefix: &str) -> Range {
+    let entries = under(sorted, prefix);
+    let mut hasher = blake3::Hasher::new();
+    for e in entries {
+        hasher.update(e.digest.as_bytes());
+    }
+    Range {
+        prefix: prefix.to_string(),
+        count: entries.len(),
+        fingerprint: hasher.finalize().to_hex().to_string(),
+        entries: (entries.len() <= LEAF_MAX).then(|| entries.to_vec()),
+    }
+}
+
+/// Summaries of `sorted` (see [`sorted`]) for each of `prefixes`.
+pub fn ranges(sorted: &[Entry], prefixes: &[String]) -> Result<Vec<Range>> {
+    if prefixes.len() > MAX_PREFIXES {
+        return Err(anyhow!(
+            "at most {MAX_PREFIXES} prefixes per request (got {})",
+            prefixes.len()
+        ));
+    }
+    prefixes
+        .iter()
+        .map(|p| {
+            check_prefix(p)?;
+            Ok(summarize(sorted, p))
+        })
+        .collect()
+}
+
+/// Reconcile `local` (see [`sorted`]) against a peer whose ranges `remote`
+/// returns, one call per round of at most [`MAX_PREFIXES`] prefixes.
+pub fn diff_with(
+    local: &[Entry],
+    mut remote: impl FnMut(&[String]) -> Result<Vec<Range>>,
+) -> Result<Diff> {
+    let mut diff = Diff::default();
+    let mut frontier = vec![String::new()];
+    while !frontier.is_empty() {
+        let mut next = Vec::new();
+        for batch in frontier.chunks(MAX_PREFIXES) {
+            let theirs = remote(batch)?;
+            diff.rounds += 1;
+            if theirs.len() != batch.len() || theirs.iter().zip(batch).any(|(r, p)| r.prefix != *p)
+            {
+                return Err(anyhow!("peer answered for different prefixes"));
+            }
+            for theirs in theirs {
+                let ours = summarize(local, &theirs.prefix);
+                if ours.count == theirs.count && ours.fingerprint == theirs.fingerprint {
+                    continue;
+                }
+                match theirs.entries {
+                    Some(remote_entries) => {
+                        let mine = under(local, &theirs.prefix);
+                        let have: HashSet<&str> = mine.iter().map(|e| e.digest.as_str()).collect();
+                        let they_have: HashSet<&str> =
+                            remote_entries.iter().map(|e| e.digest.as_str()).collect();
+                        diff.local_only.extend(
+                            mine.iter()
+                                .filter(|e| !they_have.contains(e.digest.as_str()))
+                                .cloned(),
+                        );
+                        diff.remote_only.extend(
+                            remote_entries
+                                .into_iter()
+                                .filter(|e| !have.contains(e.digest.as_str())),
+                        );
+                    }
+                    None if theirs.prefix.len() == 64 => {
+                        return Err(anyhow!(
+                            "peer sent no entries for full digest {}",
+                            theirs.prefix
+                        ));
+                    }
+                    None => next.extend(
+                        HEX.iter()
+                            .map(|&h| format!("{}{}", theirs.prefix, h as char)),
+                    ),
+                }
+            }
+        }
+        frontier = next;
+    }
+    Ok(diff)
+}
+
+/// What reconciling with a peer transferred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconcileReport {
+    pub peer: String,
+    pub diff: Diff,
+    /// Remote-only objects downloaded and ingested (or staged).
+    pub fetched: usize,
+    /// Remote-only objects left out because this node collected or staged them.
+    pub skipped: usize,
+    /// Remote-only objects that failed verification, with the reason.
+    pub rejected: Vec<(String, String)>,
+    pub staged: bool,
+}
+
+/// Reconcile the local ledger with the peer at `base`.
+///
+/// Unless `dry_run`, what only the peer holds is fetched, checked and
+/// admitted as [`pull::pull`] does. Local-only entries are reported for the peer to reconcile itself.
+pub fn reconcile(base: &str, dry_run: bool) -> Result<ReconcileReport> {
+    let base = pull::api_base(base);
+    let head = pull::head(&base)?;
+    let stage = pull::admission(&head.node)?;
+    let diff = diff_with(&ledger::sorted_by_digest()?, |prefixes| {
+        let body = pull::fetch(&format!(
+            "{base}/ledger/ranges?prefixes={}",
+            prefixes.join(",")
+        ))?;
+        Ok(serde_json::from_str::<Ranges>(&body)?.ranges)
+    })?;
+    let (mut fetched, mut skipped, mut rejected) = (0, 0, Vec::new());
+    if !dry_run {
+        for entry in &diff.remote_only {
+            if ledger::contains(&entry.digest)?
+                || (stage && ledger::staging::get(&entry.digest)?.is_some())
+            {
+                skipped += 1;
+                continue;
+            }
+            match pull::mirror(&base, entry, stage.then_some(head.node.as_str()))? {
+                Some(why) => rejected.push((entry.digest.clone(), why)),
+                None => fetched += 1,
+            }
+        }
+    }
+    Ok(ReconcileReport {
+        peer: head.node,
+        diff,
+        fetched,
+        skipped,
+        rejected,
+        staged: stage,
+    })
+}
@@ -0,0 +1,145 @@
+//! Fixtures shared by the integration tests.
+#![allow(dead_code)]
+
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signer};
+use std::path::Path;
+use std::process::{Child, Command};
+use std::time::Duration;
+use vaultmesh::ops::{self, Actor as OpsActor, Build, Env, Merkle, Op, Sign};
+use vaultmesh::receipt::{self, Actor, Receipt, Subject};
+use vaultmesh::{ledger, schema};
+
+/// Deterministic ed25519 keypair whose secret is `seed` repeated.
+pub fn keypair(seed: u8) -> Keypair {
+    let secret = SecretKey::from_bytes(&[seed; 32]).unwrap();
+    let public = PublicKey::from(&secret);
+    Keypair { secret, public }
+}
+
+/// Unsigned `demo` receipt for `digest`.
+pub fn receipt(digest: &str) -> Receipt {
+    Receipt {
+        version: None,
+        actor: Actor {
+            id: "did:test:actor".into(),
+        },
+        env: std::collections::BTreeMap::default(),
+        ts: "2024-01-01T00:00:00Z".into(),
+        subject: Subject {
+            kind: "demo".into(),
+            digest: digest.into(),
+            meta: None,
+        },
+        sign: None,
+        provenance: None,
+        provenance_ref: None,
+    }
+}
+
+/// [`receipt`] signed with `keypair(seed)`.
+pub fn signed_receipt(digest: &str, seed: u8) -> Receipt {
+    receipt::sign_receipt(receipt(digest), &keypair(seed)).unwrap()
+}
+
+/// Store `r` in the current ledger as a `receipt` entry.
+pub fn add_receipt(r: &Receipt) -> String {
+    ledger::add_json("receipt", &serde_json::to_vec(r).unwrap(), None, None).unwrap()
+}
+
+/// Ops receipt signed with `keypair(21)`, approved by `approvals`.
+pub fn ops_receipt(approvals: &[&str]) -> ops::Receipt {
+    let rec = ops::Receipt {
+        id: "01HZZZZZZZZZZZZZZZZZZZZZZZ".into(),
+        ts: "2024-01-01T00:00:00Z".into(),
+        version: Some(schema::OPS_RECEIPT_VERSION.into()),
+        prev: None,
+        actor: OpsActor {
+            id: "did:test:actor".into(),
+            cap: vec!["tf:apply".into()],
+            sig: String::new(),
+        },
+        op: Op {
+            kind: "terraform".into(),
+            target: "ws-prod".into(),
+            approvals: approvals.iter().map(|a| (*a).to_string()).collect(),
+            plan_hash: "aa".repeat(32),
+            apply_hash: "bb".repeat(32),
+            ..Default::default()
+        },
+        build: Build {
+            repo: "org/infra".into(),
+            commit: "abc123".into(),
+            binary_hash: "dev-binary".into(),
+        },
+        env: Env::default(),
+        sign: Sign {
+            alg: "ed25519".into(),
+            signature: String::new(),
+            public_key: String::new(),
+        },
+        leaf: String::new(),
+        merkle: Merkle::default(),
+    };
+    sign_ops(rec)
+}
+
+/// Re-sign `rec` with `keypair(21)` and recompute its leaf.
+pub fn sign_ops(mut rec: ops::Receipt) -> ops::Receipt {
+    let kp = keypair(21);
+    rec.sign.public_key = general_purpose::STANDARD.encode(kp.public.as_bytes());
+    let sig = kp.sign(ops::canonical_payload_json(&rec).as_bytes());
+    rec.sign.signature = general_purpose::STANDARD.encode(sig.to_bytes());
+    rec.leaf = ops::canonical_leaf_hex(&rec);
+    rec
+}
+
+/// A `vaultmesh gateway` child process, killed on drop.
+pub struct Gateway {
+    child: Child,
+    pub url: String,
+}
+
+impl Drop for Gateway {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Start `vaultmesh gateway <extra>` on a free port, serving `ledger` with
+/// its actor key under `home`, and wait until it answers.
+pub fn spawn_gateway(ledger: &Path, home: &Path, extra: &[&str]) -> Gateway {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let addr = format!("127.0.0.1:{port}");
+    let child = Command::new(env!("CARGO_BIN_EXE_vaultmesh"))
+        .args(["gateway", "--addr", &addr])
+        .args(extra)
+        .env("VAULTMESH_LEDGER_DIR", ledger)
+        .env("HOME", home)
+        .env_remove("VM_ACTOR_KEY_PATH")
+        .spawn()
+        .unwrap();
+    let gateway = Gateway {
+        child,
+        url: format!("http://{addr}"),
+    };
+    for _ in 0..100 {
+        if ureq::get(&format!("{}/v1/health", gateway.url))
+            .call()
+            .is_ok()
+        {
+            return gateway;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    panic!("gateway did not start");
+}
+
+pub fn get_json(url: &str) -> serde_json::Value {
+    serde_json::from_str(&ureq::get(url).call().unwrap().into_string().unwrap()).unwrap()
+}
@@ -0,0 +1,42 @@
+use tempfile::tempdir;
+use vaultmesh::ledger;
+
+#[test]
+fn add_document_classifies_and_records_commit_ref() {
+    let dir = tempdir().unwrap();
+    std::env::set_var("VAULTMESH_LEDGER_DIR", dir.path());
+
+    let receipt = serde_json::json!({
+        "actor": {"id":"did:test:actor"},
+        "env": {"git_commit":"abc","git_ref":"refs/heads/main"},
+        "ts": "2024-01-01T00:00:00Z",
+        "subject": {"kind":"demo","digest":"deadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef"}
+    });
+    let provenance = serde_json::json!({
+        "artifact": "artifact.bin",
+        "artifact_hash": "deadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef",
+        "actor": {"id":"did:test:actor"},
+        "build": {"commit":"abc","ref":"refs/heads/main"},
+        "ci": {},
+        "ts": {"built": "2024-01-01T00:00:00Z"}
+    });
+    let r = ledger::add_document(receipt.to_string().as_bytes()).unwrap();
+    let p = ledger::add_document(provenance.to_string().as_bytes()).unwrap();
+    assert!(ledger::add_document(b"{\"hello\":1}").is_err());
+    assert!(ledger::add_document(b"not json").is_err());
+
+    let by_commit = ledger::query(&ledger::Filter {
+        commit: Some("abc".into()),
+        ..Default::default()
+    })
+    .unwrap();
+    let got: Vec<(&str, &str)> = by_commit
+        .iter()
+        .map(|e| (e.digest.as_str(), e.kind.as_str()))
+        .collect();
+    assert_eq!(got, vec![(r.as_str(), "receipt"), (p.as_str(), "provenance")]);
+    assert!(by_commit
+        .iter()
+        .all(|e| e.git_ref.as_deref() == Some("refs/heads/main")));
+    assert_eq!(ledger::get_json(&r).unwrap(), receipt.to_string().as_bytes());
+}
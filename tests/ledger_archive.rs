@@ -0,0 +1,128 @@
+mod common;
+
+use common::keypair;
+use std::io::Read;
+use std::path::Path;
+use tempfile::tempdir;
+use vaultmesh::identity::did_key_from_public;
+use vaultmesh::ledger::{self, archive};
+use vaultmesh::receipt;
+use vaultmesh::sync::{peers, PeerInfo, TrustLevel};
+
+/// Copy `src` to `dst`, passing each member through `edit`.
+fn rewrite(src: &Path, dst: &Path, edit: impl Fn(&str, Vec<u8>) -> Vec<u8>) {
+    let mut out = tar::Builder::new(std::fs::File::create(dst).unwrap());
+    let mut tar = tar::Archive::new(std::fs::File::open(src).unwrap());
+    for entry in tar.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let name = entry.path().unwrap().to_string_lossy().to_string();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        let data = edit(&name, data);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_cksum();
+        out.append_data(&mut header, &name, data.as_slice())
+            .unwrap();
+    }
+    out.finish().unwrap();
+}
+
+#[test]
+fn export_import_round_trip_and_rejects_tampering() {
+    let work = tempdir().unwrap();
+    let source = tempdir().unwrap();
+    let dirty = tempdir().unwrap();
+    let target = tempdir().unwrap();
+    let kp = keypair(11);
+    let bundle = work.path().join("bundle.tar");
+    let unchecked = work.path().join("unchecked.tar");
+
+    std::env::set_var("VAULTMESH_LEDGER_DIR", source.path());
+    let mut rcpt = common::receipt(&"de".repeat(32));
+    rcpt.env.insert("git_commit".into(), "abc".into());
+    rcpt.env.insert("git_ref".into(), "refs/heads/main".into());
+    let rcpt = receipt::sign_receipt(rcpt, &keypair(13)).unwrap();
+    let r = ledger::add_document(&serde_json::to_vec(&rcpt).unwrap()).unwrap();
+    let provenance = serde_json::json!({
+        "artifact": "artifact.bin",
+        "artifact_hash": "de".repeat(32),
+        "actor": {"id":"did:test:actor"},
+        "build": {},
+        "ci": {},
+        "ts": {"built": "2024-01-01T00:00:00Z"}
+    });
+    let p = ledger::add_document(provenance.to_string().as_bytes()).unwrap();
+    // Objects of no known kind are not exported, so the archive stays importable.
+    let unknown = ledger::add_json("", br#"{"note":"scratch"}"#, None, None).unwrap();
+    assert_eq!(ledger::list().unwrap()[2].kind, "unknown");
+    let future = chrono::Utc::now() + chrono::Duration::minutes(1);
+    assert_eq!(
+        archive::export(&bundle, Some(future), &kp).unwrap().count,
+        0
+    );
+    let manifest = archive::export(&bundle, None, &kp).unwrap();
+    assert_eq!(manifest.count, 2);
+    manifest.verify().unwrap();
+
+    // Correctly signed archives whose objects would not pass a pull.
+    std::env::set_var("VAULTMESH_LEDGER_DIR", dirty.path());
+    common::add_receipt(&common::receipt(&"ef".repeat(32)));
+    archive::export(&unchecked, None, &kp).unwrap();
+
+    std::env::set_var("VAULTMESH_LEDGER_DIR", target.path());
+    let signer = did_key_from_public(kp.public.as_bytes());
+    let tampered = work.path().join("tampered.tar");
+    rewrite(&bundle, &tampered, |name, data| {
+        if name.ends_with(&format!("{p}.json")) {
+            String::from_utf8(data)
+                .unwrap()
+                .replace("artifact.bin", "artifact.exe")
+                .into_bytes()
+        } else {
+            data
+        }
+    });
+    assert!(archive::import(&tampered, Some(&signer)).is_err());
+    rewrite(&bundle, &tampered, |name, data| {
+        if name == "index.jsonl" {
+            String::from_utf8(data)
+                .unwrap()
+                .replace("abc", "abd")
+                .into_bytes()
+        } else {
+            data
+        }
+    });
+    assert!(archive::import(&tampered, Some(&signer)).is_err());
+    let other = did_key_from_public(keypair(12).public.as_bytes());
+    assert!(archive::import(&bundle, Some(&other)).is_err());
+    let err = archive::import(&unchecked, Some(&signer)).unwrap_err();
+    assert!(err.to_string().contains("rejected"), "{err}");
+
+    // Without --signer only a full peer's archive is accepted.
+    let err = archive::import(&bundle, None).unwrap_err();
+    assert!(err.to_string().contains("not a full peer"), "{err}");
+    peers::add(PeerInfo {
+        id: signer.clone(),
+        url: "http://peer.example:8080".into(),
+        trust: TrustLevel::Quarantine,
+    })
+    .unwrap();
+    assert!(archive::import(&bundle, None).is_err());
+    assert!(ledger::list().unwrap().is_empty());
+
+    let report = archive::import(&bundle, Some(&signer)).unwrap();
+    assert_eq!(report.added, [r, p.clone()]);
+    assert!(!ledger::contains(&unknown).unwrap());
+    let entries = ledger::list().unwrap();
+    assert_eq!(entries[0].kind, "receipt");
+    assert_eq!(entries[0].commit.as_deref(), Some("abc"));
+    assert_eq!(entries[1].kind, "provenance");
+    assert!(ledger::get_json(&p).is_ok());
+
+    peers::set_trust(&signer, TrustLevel::Full).unwrap();
+    let again = archive::import(&bundle, None).unwrap();
+    assert!(again.added.is_empty());
+    assert_eq!(again.present, 2);
+}
@@ -0,0 +1,159 @@
+use tempfile::tempdir;
+use vaultmesh::{jcs, ledger, merkle, receipt};
+
+#[test]
+#[allow(clippy::too_many_lines)]
+fn ingest_paths_agree_and_migration_rekeys_raw_objects() {
+    let dir = tempdir().unwrap();
+    std::env::set_var("VAULTMESH_LEDGER_DIR", dir.path());
+
+    let doc = serde_json::json!({
+        "actor": {"id":"did:test:actor"},
+        "env": {"git_ref":"main","git_commit":"abc"},
+        "ts": "2024-01-01T00:00:00Z",
+        "subject": {"kind":"demo","digest":"deadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef"}
+    });
+    let canonical = jcs::to_vec(&doc);
+    let expected = receipt::blake3_hex(&canonical);
+
+    // A pretty file (ledger add / sync pull) and a re-serialized Value (gateway)
+    // land on the same digest.
+    let pretty = serde_json::to_vec_pretty(&doc).unwrap();
+    let compact = serde_json::to_vec(&doc).unwrap();
+    assert_eq!(
+        ledger::add_json("receipt", &pretty, None, None).unwrap(),
+        expected
+    );
+    assert_eq!(
+        ledger::add_json("receipt", &compact, None, None).unwrap(),
+        expected
+    );
+    assert_eq!(ledger::get_json(&expected).unwrap(), canonical);
+    assert_eq!(ledger::list().unwrap().len(), 1);
+
+    // Objects stored by older nodes under raw-byte digests get re-keyed.
+    let index = dir.path().join("index.jsonl");
+    let append_raw = |v: &serde_json::Value, kind: &str, seq: u64| {
+        let raw = serde_json::to_vec_pretty(v).unwrap();
+        let raw_digest = receipt::blake3_hex(&raw);
+        std::fs::write(dir.path().join(format!("{raw_digest}.json")), &raw).unwrap();
+        let line = format!(
+            "{{\"seq\":{seq},\"digest\":\"{raw_digest}\",\"kind\":\"{kind}\",\"ts\":\"2024-01-01T00:00:00Z\"}}\n"
+        );
+        let mut idx = std::fs::read(&index).unwrap();
+        idx.extend(line.as_bytes());
+        std::fs::write(&index, idx).unwrap();
+        raw_digest
+    };
+    let other = serde_json::json!({"artifact":"a","artifact_hash":"00"});
+    let raw_digest = append_raw(&other, "unknown", 1);
+    // A receipt signed before the migration keeps naming the raw digest.
+    let mut referrer = doc.clone();
+    referrer["subject"]["digest"] = serde_json::json!("ab".repeat(32));
+    referrer["provenance_ref"] = serde_json::json!({"path":"p.json","digest":raw_digest});
+    let referrer =
+        ledger::add_json("receipt", referrer.to_string().as_bytes(), None, None).unwrap();
+    let mut raw_receipt = doc;
+    raw_receipt["subject"]["digest"] = serde_json::json!("cd".repeat(32));
+    let raw_receipt_digest = append_raw(&raw_receipt, "receipt", 3);
+    let (old_root, old_size) = ledger::merkle_root().unwrap();
+    let old_leaves = ledger::receipt_leaves().unwrap();
+    assert_eq!(old_size, 3);
+
+    let report = ledger::migrate_canonical().unwrap();
+    let new_digest = receipt::blake3_hex(&jcs::to_vec(&other));
+    let new_receipt = receipt::blake3_hex(&jcs::to_vec(&raw_receipt));
+    assert_eq!(
+        report.rekeyed,
+        vec![
+            (raw_digest.clone(), new_digest.clone()),
+            (raw_receipt_digest.clone(), new_receipt.clone())
+        ]
+    );
+    assert_eq!(report.unchanged, 2);
+    assert!(!dir.path().join(format!("{raw_digest}.json")).exists());
+
+    // Old entries are tombstoned in place and the canonical objects appended:
+    // the old receipt leaf stays, so the new tree extends the old one.
+    let digests: Vec<String> = ledger::list()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.digest)
+        .collect();
+    assert_eq!(
+        digests,
+        vec![expected, referrer, new_digest.clone(), new_receipt.clone()]
+    );
+    assert_eq!(report.old_root, (old_root.clone(), 3));
+    assert_eq!(report.new_root, ledger::merkle_root().unwrap());
+    assert_eq!(report.new_root.1, 4);
+    let leaves = ledger::receipt_leaves().unwrap();
+    assert_eq!(leaves[..3], old_leaves[..]);
+    assert_eq!(leaves[3], new_receipt);
+    let proof = ledger::consistency_proof(3, 4).unwrap();
+    assert!(merkle::verify_consistency(3, 4, &old_root, &report.new_root.0, &proof).unwrap());
+    assert!(ledger::contains(&raw_receipt_digest).unwrap());
+
+    // The old digest resolves through the alias: fsck finds no dangling
+    // reference and gc keeps the re-keyed object for the live receipt.
+    let aliases = std::fs::read_to_string(dir.path().join("aliases.jsonl")).unwrap();
+    assert!(aliases.contains(&raw_digest) && aliases.contains(&new_digest));
+    let fsck = ledger::fsck().unwrap();
+    assert!(
+        !fsck
+            .problems
+            .iter()
+            .any(|(_, p)| matches!(p, ledger::Problem::DanglingRef(_))),
+        "{:?}",
+        fsck.problems
+    );
+    let unknown = ledger::Retention {
+        kinds: vec!["unknown".into()],
+        ..Default::default()
+    };
+    let gc = ledger::gc(&unknown, true).unwrap();
+    assert!(gc.collected.is_empty());
+    assert_eq!(gc.kept_referenced, 1);
+
+    // A migration by another process appends to the index; this process
+    // picks up the new entries on its next read.
+    let stray = serde_json::json!({"artifact":"b","artifact_hash":"01"});
+    let raw_digest = append_raw(&stray, "unknown", 8);
+    assert_eq!(ledger::list().unwrap().last().unwrap().digest, raw_digest);
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_vaultmesh"))
+        .args(["ledger", "migrate"])
+        .env("VAULTMESH_LEDGER_DIR", dir.path())
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        stdout.contains(&format!(
+            "ROOT {0} (4 leaves)  ->  {0} (4 leaves)",
+            report.new_root.0
+        )),
+        "{stdout}"
+    );
+    let new_digest = receipt::blake3_hex(&jcs::to_vec(&stray));
+    let live = ledger::list().unwrap();
+    assert_eq!(live.last().unwrap().digest, new_digest);
+    assert!(!live.iter().any(|e| e.digest == raw_digest));
+    assert!(ledger::get_json(&new_digest).is_ok());
+
+    // An index replaced underneath this process, to the same length with
+    // different digests, is reloaded on the next read.
+    let rewritten = std::fs::read_to_string(&index)
+        .unwrap()
+        .replace(&new_digest, &"ef".repeat(32));
+    let tmp = dir.path().join("index.jsonl.tmp");
+    std::fs::write(&tmp, rewritten).unwrap();
+    std::fs::rename(tmp, &index).unwrap();
+    assert_eq!(
+        ledger::list().unwrap().last().unwrap().digest,
+        "ef".repeat(32)
+    );
+}
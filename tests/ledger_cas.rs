@@ -0,0 +1,29 @@
+use tempfile::tempdir;
+use vaultmesh::ledger;
+
+#[test]
+fn objects_are_sharded_verified_on_read_and_legacy_flat_files_still_resolve() {
+    let dir = tempdir().unwrap();
+    std::env::set_var("VAULTMESH_LEDGER_DIR", dir.path());
+
+    let d = ledger::add_json("", b"{\"n\":1}", None, None).unwrap();
+    let shard = dir.path().join(&d[..2]);
+    let path = shard.join(format!("{}.json", &d[2..]));
+    assert_eq!(std::fs::read(&path).unwrap(), b"{\"n\":1}");
+    assert!(!dir.path().join(format!("{d}.json")).exists());
+    // Only the object itself is left in the shard; no temp files.
+    assert_eq!(std::fs::read_dir(&shard).unwrap().count(), 1);
+
+    // A flat object from an older node is still served.
+    let legacy = b"{\"n\":2}";
+    let legacy_digest = vaultmesh::receipt::blake3_hex(legacy);
+    std::fs::write(dir.path().join(format!("{legacy_digest}.json")), legacy).unwrap();
+    assert_eq!(ledger::get_json(&legacy_digest).unwrap(), legacy);
+
+    // Bit rot under a valid name is refused rather than served.
+    std::fs::write(&path, b"{\"n\":9}").unwrap();
+    let err = ledger::get_json(&d).unwrap_err().to_string();
+    assert!(err.contains("corrupt"), "{err}");
+
+    assert!(ledger::get_json("../index.jsonl").is_err());
+}
@@ -12,11 +12,11 @@ fn classify_receipt_and_provenance() {
         "actor": {"id":"did:test:actor"},
         "env": {"git_commit":"abc","git_ref":"main"},
         "ts": "2024-01-01T00:00:00Z",
-        "subject": {"kind":"demo","digest":"deadbeef"}
+        "subject": {"kind":"demo","digest":"deadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef"}
     });
     let provenance = serde_json::json!({
         "artifact": "artifact.bin",
-        "artifact_hash": "deadbeef",
+        "artifact_hash": "deadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef",
         "actor": {"id":"did:test:actor"},
         "build": {},
         "ci": {},
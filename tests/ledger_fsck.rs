@@ -0,0 +1,74 @@
+mod common;
+
+use common::keypair;
+use tempfile::tempdir;
+use vaultmesh::ledger::{self, Problem};
+use vaultmesh::receipt::{self, ProvenanceRef, Receipt};
+
+fn receipt(digest: &str, provenance: Option<&str>) -> Receipt {
+    Receipt {
+        provenance_ref: provenance.map(|d| ProvenanceRef {
+            path: "provenance.json".into(),
+            digest: d.into(),
+        }),
+        ..common::receipt(digest)
+    }
+}
+
+fn shard_path(dir: &std::path::Path, digest: &str) -> std::path::PathBuf {
+    dir.join(&digest[..2])
+        .join(format!("{}.json", &digest[2..]))
+}
+
+#[test]
+fn fsck_reports_each_class_of_damage() {
+    let dir = tempdir().unwrap();
+    std::env::set_var("VAULTMESH_LEDGER_DIR", dir.path());
+    let kp = keypair(5);
+    let add = |v: &serde_json::Value| ledger::add_document(v.to_string().as_bytes()).unwrap();
+
+    let provenance = serde_json::json!({
+        "artifact": "artifact.bin",
+        "artifact_hash": "deadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef",
+        "actor": {"id":"did:test:actor"},
+        "build": {},
+        "ci": {},
+        "ts": {"built": "2024-01-01T00:00:00Z"}
+    });
+    let prov = add(&provenance);
+    let signed = receipt::sign_receipt(receipt(&"aa".repeat(32), Some(&prov)), &kp).unwrap();
+    add(&serde_json::to_value(&signed).unwrap());
+
+    let report = ledger::fsck().unwrap();
+    assert!(report.is_ok(), "{:?}", report.problems);
+    assert_eq!(report.checked, 2);
+
+    let unsigned = add(&serde_json::to_value(receipt(&"bb".repeat(32), None)).unwrap());
+    let dangling_ref = "ff".repeat(32);
+    let dangling = receipt::sign_receipt(receipt(&"cc".repeat(32), Some(&dangling_ref)), &kp).unwrap();
+    let dangling = add(&serde_json::to_value(&dangling).unwrap());
+    let rotten = receipt::sign_receipt(receipt(&"dd".repeat(32), None), &kp).unwrap();
+    let rotten = add(&serde_json::to_value(&rotten).unwrap());
+    std::fs::write(shard_path(dir.path(), &rotten), b"{}").unwrap();
+    std::fs::remove_file(shard_path(dir.path(), &prov)).unwrap();
+    let orphan_bytes = b"{\"stray\":true}";
+    let orphan = receipt::blake3_hex(orphan_bytes);
+    std::fs::create_dir_all(dir.path().join(&orphan[..2])).unwrap();
+    std::fs::write(shard_path(dir.path(), &orphan), orphan_bytes).unwrap();
+
+    let report = ledger::fsck().unwrap();
+    let found = |digest: &str, pred: fn(&Problem) -> bool| {
+        report.problems.iter().any(|(d, p)| d == digest && pred(p))
+    };
+    assert!(found(&unsigned, |p| matches!(p, Problem::Signature(_))));
+    assert!(found(&dangling, |p| *p == Problem::DanglingRef("ff".repeat(32))));
+    assert!(found(&rotten, |p| matches!(p, Problem::BadDigest { .. })));
+    assert!(found(&rotten, |p| matches!(p, Problem::Schema(_))));
+    assert!(found(&prov, |p| *p == Problem::Missing));
+    assert!(found(&orphan, |p| *p == Problem::Orphan));
+    // The healthy receipt now points at the deleted provenance.
+    assert!(report
+        .problems
+        .iter()
+        .any(|(_, p)| *p == Problem::DanglingRef(prov.clone())));
+}
@@ -0,0 +1,186 @@
+mod common;
+
+use common::keypair;
+use tempfile::tempdir;
+use vaultmesh::ledger::{self, Problem, Retention};
+use vaultmesh::{merkle, sth};
+
+fn receipt(digest: &str, provenance: Option<&str>) -> Vec<u8> {
+    let mut v = serde_json::json!({
+        "actor": {"id":"did:test:actor"},
+        "env": {},
+        "ts": "2024-01-01T00:00:00Z",
+        "subject": {"kind":"demo","digest":digest}
+    });
+    if let Some(d) = provenance {
+        v["provenance_ref"] = serde_json::json!({"path":"provenance.json","digest":d});
+    }
+    v.to_string().into_bytes()
+}
+
+/// The fixtures are unsigned; anything else fsck finds is a gc bug.
+fn fsck_clean() -> bool {
+    let report = ledger::fsck().unwrap();
+    report
+        .problems
+        .iter()
+        .all(|(_, p)| matches!(p, Problem::Signature(_)))
+}
+
+fn digests(entries: &[ledger::Entry]) -> Vec<&str> {
+    entries.iter().map(|e| e.digest.as_str()).collect()
+}
+
+#[test]
+fn gc_honours_age_kind_seals_and_provenance_refs() {
+    let dir = tempdir().unwrap();
+    std::env::set_var("VAULTMESH_LEDGER_DIR", dir.path());
+
+    let provenance = serde_json::json!({
+        "artifact": "artifact.bin",
+        "artifact_hash": "deadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef",
+        "actor": {"id":"did:test:actor"},
+        "build": {},
+        "ci": {},
+        "ts": {"built": "2024-01-01T00:00:00Z"}
+    });
+    let prov = ledger::add_document(provenance.to_string().as_bytes()).unwrap();
+    let sealed = ledger::add_document(&receipt(&"aa".repeat(32), Some(&prov))).unwrap();
+    let loose = ledger::add_document(&receipt(&"bb".repeat(32), None)).unwrap();
+    let unknown = ledger::add_json("", b"{\"note\":1}", None, None).unwrap();
+
+    let leaves = ledger::receipt_leaves().unwrap();
+    let head_root = merkle::root(ledger::TREE_ALG, &leaves[..1]).unwrap();
+    let head = sth::sign(ledger::TREE_ALG, 1, &head_root, &keypair(7)).unwrap();
+    let (root_before, size_before) = ledger::merkle_root().unwrap();
+
+    let future = Some(chrono::Utc::now() + chrono::Duration::minutes(1));
+    let past = Some(chrono::Utc::now() - chrono::Duration::days(1));
+    assert!(ledger::gc(&Retention::default(), true).is_err());
+
+    // Nothing is old enough yet.
+    let none = Retention {
+        older_than: past,
+        ..Default::default()
+    };
+    assert!(ledger::gc(&none, false).unwrap().collected.is_empty());
+
+    let all = Retention {
+        older_than: future,
+        sealed: vec![head.clone()],
+        ..Default::default()
+    };
+    let dry = ledger::gc(&all, true).unwrap();
+    assert_eq!(digests(&dry.collected), [loose.as_str(), unknown.as_str()]);
+    assert_eq!((dry.kept_sealed, dry.kept_referenced), (1, 1));
+    assert!(ledger::get_json(&loose).is_ok());
+    assert_eq!(ledger::list().unwrap().len(), 4);
+
+    let report = ledger::gc(&all, false).unwrap();
+    assert_eq!(report.collected, dry.collected);
+    assert!(ledger::get_json(&loose).is_err());
+    assert!(ledger::get_json(&unknown).is_err());
+    assert_eq!(
+        digests(&ledger::list().unwrap()),
+        [prov.as_str(), sealed.as_str()]
+    );
+    // The tree is append-only: collecting a receipt does not change the root,
+    // and its earlier inclusion still proves.
+    assert_eq!(ledger::merkle_root().unwrap(), (root_before, size_before));
+    assert!(ledger::inclusion_proof(&loose)
+        .unwrap()
+        .unwrap()
+        .verify()
+        .unwrap());
+    assert!(fsck_clean());
+
+    // Provenance stays while the receipt naming it is live.
+    let provenance_only = Retention {
+        kinds: vec!["provenance".into()],
+        ..Default::default()
+    };
+    let report = ledger::gc(&provenance_only, false).unwrap();
+    assert!(report.collected.is_empty());
+    assert_eq!(report.kept_referenced, 1);
+
+    // A tree head that is not over this ledger is refused.
+    let mut forged = head;
+    forged.root = "00".repeat(32);
+    let bad = Retention {
+        kinds: vec!["receipt".into()],
+        sealed: vec![forged],
+        ..Default::default()
+    };
+    assert!(ledger::gc(&bad, true).is_err());
+
+    let everything = Retention {
+        kinds: vec!["receipt".into(), "provenance".into()],
+        ..Default::default()
+    };
+    let report = ledger::gc(&everything, false).unwrap();
+    assert_eq!(digests(&report.collected), [prov.as_str(), sealed.as_str()]);
+    assert!(ledger::list().unwrap().is_empty());
+
+    // Re-ingesting a collected object makes it live again; the index survives
+    // a reload with its tombstones.
+    ledger::add_document(&receipt(&"bb".repeat(32), None)).unwrap();
+    assert_eq!(digests(&ledger::list().unwrap()), [loose.as_str()]);
+    assert!(fsck_clean());
+    let index = std::fs::read_to_string(dir.path().join("index.jsonl")).unwrap();
+    assert_eq!(index.matches("\"tombstone\"").count(), 4);
+    assert_eq!(ledger::merkle_root().unwrap().1, size_before + 1);
+}
+
+#[test]
+fn keep_sealed_takes_ledger_tree_heads_not_seal_docs() {
+    let (ledger_dir, home, work) = (tempdir().unwrap(), tempdir().unwrap(), tempdir().unwrap());
+    let vaultmesh = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_vaultmesh"))
+            .args(args)
+            .env("VAULTMESH_LEDGER_DIR", ledger_dir.path())
+            .env("HOME", home.path())
+            .env_remove("VM_ACTOR_KEY_PATH")
+            .output()
+            .unwrap()
+    };
+    let path = |name: &str| work.path().join(name).to_str().unwrap().to_string();
+    std::fs::create_dir(path("receipts")).unwrap();
+
+    // A seal's tree head is over the receipt dir, so it cannot pin the ledger.
+    let out = vaultmesh(&[
+        "seal",
+        "--date",
+        "2025-01-01",
+        "--alg",
+        "rfc6962",
+        "--dir",
+        &path("receipts"),
+        "--out",
+        &path("seal.json"),
+    ]);
+    assert!(out.status.success());
+    let gc = |sealed: &str| {
+        vaultmesh(&[
+            "ledger",
+            "gc",
+            "--kind",
+            "receipt",
+            "--keep-sealed",
+            sealed,
+            "--dry-run",
+        ])
+    };
+    let out = gc(&path("seal.json"));
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("/v1/sth"));
+
+    let root = merkle::root(ledger::TREE_ALG, &[]).unwrap();
+    let head = sth::sign(ledger::TREE_ALG, 0, &root, &keypair(7)).unwrap();
+    std::fs::write(path("sth.json"), serde_json::to_vec(&head).unwrap()).unwrap();
+    let out = gc(&path("sth.json"));
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}
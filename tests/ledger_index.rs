@@ -0,0 +1,128 @@
+use std::io::Write;
+use tempfile::tempdir;
+use vaultmesh::{ledger, merkle};
+
+fn receipt(i: u32) -> Vec<u8> {
+    serde_json::to_vec(&serde_json::json!({
+        "actor": {"id":"did:test:actor"},
+        "env": {},
+        "ts": "2024-01-01T00:00:00Z",
+        "subject": {"kind":"demo","digest": format!("{i:064x}")}
+    }))
+    .unwrap()
+}
+
+#[test]
+fn index_is_append_only_and_drives_the_root() {
+    let dir = tempdir().unwrap();
+    std::env::set_var("VAULTMESH_LEDGER_DIR", dir.path());
+
+    let a = ledger::add_json(
+        "receipt",
+        &receipt(1),
+        Some("abc".into()),
+        Some("refs/heads/main".into()),
+    )
+    .unwrap();
+    let p = ledger::add_json("provenance", b"{\"artifact\":\"x\"}", None, None).unwrap();
+    let b = ledger::add_json("receipt", &receipt(2), None, None).unwrap();
+    // Re-adding an existing object does not create a second entry.
+    assert_eq!(
+        ledger::add_json("receipt", &receipt(1), None, None).unwrap(),
+        a
+    );
+
+    let entries = ledger::list().unwrap();
+    let seqs: Vec<u64> = entries.iter().map(|e| e.seq).collect();
+    assert_eq!(seqs, vec![0, 1, 2]);
+    assert_eq!(entries[0].digest, a);
+    assert_eq!(entries[0].commit.as_deref(), Some("abc"));
+    assert_eq!(entries[0].git_ref.as_deref(), Some("refs/heads/main"));
+    assert_eq!(entries[1].digest, p);
+    assert_eq!(entries[1].kind, "provenance");
+
+    // Leaves keep ingest order, not digest order.
+    assert_eq!(
+        ledger::receipt_leaves().unwrap(),
+        vec![a.clone(), b.clone()]
+    );
+    let (root, size) = ledger::merkle_root().unwrap();
+    assert_eq!(size, 2);
+    assert_eq!(root, merkle::root(ledger::TREE_ALG, &[a, b]).unwrap());
+
+    // Appends by another writer are picked up incrementally.
+    let c = hex::encode([0xab; 32]);
+    let mut index = std::fs::OpenOptions::new()
+        .append(true)
+        .open(dir.path().join("index.jsonl"))
+        .unwrap();
+    writeln!(
+        index,
+        r#"{{"seq":3,"digest":"{c}","kind":"receipt","ts":"2024-01-01T00:00:00Z"}}"#
+    )
+    .unwrap();
+    let entries = ledger::list().unwrap();
+    assert_eq!(entries.len(), 4);
+    assert_eq!(ledger::merkle_root().unwrap().1, 3);
+}
+
+#[test]
+fn filter_matches_commit_prefix_ref_and_kind() {
+    let entry = ledger::Entry {
+        seq: 0,
+        digest: "aa".into(),
+        kind: "receipt".into(),
+        ts: "2024-01-01T00:00:00Z".into(),
+        commit: Some("abcdef123".into()),
+        git_ref: Some("refs/heads/main".into()),
+    };
+    let f = |kind: Option<&str>, commit: Option<&str>, git_ref: Option<&str>| ledger::Filter {
+        kind: kind.map(Into::into),
+        commit: commit.map(Into::into),
+        git_ref: git_ref.map(Into::into),
+    };
+    assert!(f(None, None, None).matches(&entry));
+    assert!(f(Some("receipt"), Some("abcdef"), Some("refs/heads/main")).matches(&entry));
+    assert!(!f(Some("provenance"), None, None).matches(&entry));
+    assert!(!f(None, Some("bcdef"), None).matches(&entry));
+    assert!(!f(None, None, Some("main")).matches(&entry));
+
+    let bare = ledger::Entry {
+        commit: None,
+        git_ref: None,
+        ..entry
+    };
+    assert!(!f(None, Some("abc"), None).matches(&bare));
+}
+
+#[test]
+fn concurrent_writers_get_distinct_seqs() {
+    let (dir, work) = (tempdir().unwrap(), tempdir().unwrap());
+    let writers: Vec<_> = (0..4u32)
+        .map(|w| {
+            let files: Vec<_> = (0..8)
+                .map(|i| {
+                    let path = work.path().join(format!("{w}-{i}.json"));
+                    std::fs::write(&path, receipt(w * 100 + i)).unwrap();
+                    path
+                })
+                .collect();
+            std::process::Command::new(env!("CARGO_BIN_EXE_vaultmesh"))
+                .args(["ledger", "add"])
+                .args(files)
+                .env("VAULTMESH_LEDGER_DIR", dir.path())
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+    for mut w in writers {
+        assert!(w.wait().unwrap().success());
+    }
+    let index = std::fs::read_to_string(dir.path().join("index.jsonl")).unwrap();
+    let mut seqs: Vec<u64> = index
+        .lines()
+        .map(|l| serde_json::from_str::<ledger::Entry>(l).unwrap().seq)
+        .collect();
+    seqs.sort_unstable();
+    assert_eq!(seqs, (0..32).collect::<Vec<u64>>());
+}
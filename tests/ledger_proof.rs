@@ -0,0 +1,29 @@
+use tempfile::tempdir;
+
+#[test]
+fn inclusion_proofs_fold_to_ledger_root() {
+    let dir = tempdir().unwrap();
+    std::env::set_var("VAULTMESH_LEDGER_DIR", dir.path());
+
+    let mut digests = Vec::new();
+    for i in 0..5 {
+        let receipt = serde_json::json!({
+            "actor": {"id":"did:test:actor"},
+            "env": {},
+            "ts": "2024-01-01T00:00:00Z",
+            "subject": {"kind":"demo","digest": format!("{i:064x}")}
+        });
+        let bytes = serde_json::to_vec(&receipt).unwrap();
+        digests.push(vaultmesh::ledger::add_json("receipt", &bytes, None, None).unwrap());
+    }
+
+    let (root, size) = vaultmesh::ledger::merkle_root().unwrap();
+    assert_eq!(size, 5);
+    for d in &digests {
+        let proof = vaultmesh::ledger::inclusion_proof(d).unwrap().unwrap();
+        assert_eq!(proof.root, root);
+        assert_eq!(proof.size, size);
+        assert!(proof.verify().unwrap());
+    }
+    assert!(vaultmesh::ledger::inclusion_proof("00").unwrap().is_none());
+}
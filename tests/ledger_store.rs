@@ -0,0 +1,155 @@
+use std::io::Write;
+use tempfile::tempdir;
+use vaultmesh::ledger::{
+    self, lock,
+    store::{FsStore, LedgerStore, LogStore},
+};
+use vaultmesh::receipt::blake3_hex;
+
+fn exercise(store: &dyn LedgerStore) {
+    let a = blake3_hex(b"{\"a\":1}");
+    store.put(&a, b"{\"a\":1}").unwrap();
+    store.put("bb02", b"{\"b\":2}").unwrap();
+    store.put(&a, b"ignored").unwrap();
+    assert!(store.has(&a).unwrap());
+    assert_eq!(store.get(&a).unwrap().unwrap(), b"{\"a\":1}");
+    assert_eq!(store.get("cc03").unwrap(), None);
+
+    let mut all: Vec<String> = store.iter().unwrap().map(Result::unwrap).collect();
+    all.sort();
+    let mut want = [a.clone(), "bb02".to_string()];
+    want.sort();
+    assert_eq!(all, want);
+
+    assert!(store.delete(&a).unwrap());
+    assert!(!store.delete(&a).unwrap());
+    assert!(!store.has(&a).unwrap());
+
+    store.put("cc03", b"{}").unwrap();
+    let dropped = store.retain(&mut |d| d != "bb02").unwrap();
+    assert_eq!(dropped, ["bb02"]);
+    let all: Vec<String> = store.iter().unwrap().map(Result::unwrap).collect();
+    assert_eq!(all, ["cc03"]);
+}
+
+#[test]
+fn backends_share_semantics() {
+    let fs_dir = tempdir().unwrap();
+    exercise(&FsStore::new(fs_dir.path()));
+    let log_dir = tempdir().unwrap();
+    exercise(&LogStore::open(log_dir.path()).unwrap());
+}
+
+#[test]
+fn fs_store_replaces_corrupt_objects() {
+    let dir = tempdir().unwrap();
+    let store = FsStore::new(dir.path());
+    let bytes = b"{\"a\":1}";
+    let digest = blake3_hex(bytes);
+    let sharded = dir
+        .path()
+        .join(&digest[..2])
+        .join(format!("{}.json", &digest[2..]));
+    std::fs::create_dir_all(sharded.parent().unwrap()).unwrap();
+    std::fs::write(&sharded, b"{\"a\":2").unwrap();
+    store.put(&digest, bytes).unwrap();
+    assert_eq!(store.get(&digest).unwrap().unwrap(), bytes);
+
+    // A corrupt object in the old flat layout is replaced by a sharded copy.
+    let bytes = b"{\"b\":1}";
+    let digest = blake3_hex(bytes);
+    let flat = dir.path().join(format!("{digest}.json"));
+    std::fs::write(&flat, b"").unwrap();
+    store.put(&digest, bytes).unwrap();
+    assert!(!flat.exists());
+    assert_eq!(store.get(&digest).unwrap().unwrap(), bytes);
+    assert_eq!(store.iter().unwrap().count(), 2);
+}
+
+#[test]
+fn log_store_survives_reopen_torn_tail_and_compaction() {
+    let dir = tempdir().unwrap();
+    {
+        let store = LogStore::open(dir.path()).unwrap();
+        store.put("aa01", b"one").unwrap();
+        store.put("bb02", b"two").unwrap();
+        store.delete("aa01").unwrap();
+    }
+    // Simulate a crash halfway through appending a record.
+    let log = dir.path().join("objects.log");
+    let intact = std::fs::metadata(&log).unwrap().len();
+    let mut f = std::fs::OpenOptions::new().append(true).open(&log).unwrap();
+    f.write_all(&[1, 2, b'c', b'c', 9, 0, 0, 0, b'x']).unwrap();
+    drop(f);
+
+    let store = LogStore::open(dir.path()).unwrap();
+    assert_eq!(std::fs::metadata(&log).unwrap().len(), intact);
+    assert!(!store.has("aa01").unwrap());
+    assert_eq!(store.get("bb02").unwrap().unwrap(), b"two");
+
+    // A second handle sees appends made through the first.
+    let other = LogStore::open(dir.path()).unwrap();
+    store.put("cc03", b"three").unwrap();
+    assert_eq!(other.get("cc03").unwrap().unwrap(), b"three");
+
+    store.compact().unwrap();
+    assert!(std::fs::metadata(&log).unwrap().len() < intact);
+    assert_eq!(store.get("bb02").unwrap().unwrap(), b"two");
+    assert_eq!(store.get("cc03").unwrap().unwrap(), b"three");
+    let reopened = LogStore::open(dir.path()).unwrap();
+    assert_eq!(reopened.get("cc03").unwrap().unwrap(), b"three");
+}
+
+#[test]
+fn log_store_handles_share_appends_across_compaction_and_the_lock() {
+    let dir = tempdir().unwrap();
+    let (a, b) = (
+        LogStore::open(dir.path()).unwrap(),
+        LogStore::open(dir.path()).unwrap(),
+    );
+    a.put("aa01", b"one").unwrap();
+    b.put("bb02", b"two").unwrap();
+    a.delete("aa01").unwrap();
+    // `b` still has the pre-compaction file open; its next append must land
+    // in the compacted log, not in the unlinked one.
+    a.compact().unwrap();
+    b.put("cc03", b"three").unwrap();
+    assert_eq!(a.get("cc03").unwrap().unwrap(), b"three");
+    assert!(!b.has("aa01").unwrap());
+    let reopened = LogStore::open(dir.path()).unwrap();
+    assert_eq!(reopened.get("bb02").unwrap().unwrap(), b"two");
+    assert_eq!(reopened.get("cc03").unwrap().unwrap(), b"three");
+
+    // A partial record written under the lock is an append in flight: open
+    // waits for it instead of cutting it off as a torn tail.
+    let log = dir.path().join("objects.log");
+    let (started, start) = std::sync::mpsc::channel();
+    let writer = {
+        let dir = dir.path().to_path_buf();
+        std::thread::spawn(move || {
+            let _held = lock::exclusive(&dir).unwrap();
+            let mut f = std::fs::OpenOptions::new().append(true).open(&log).unwrap();
+            f.write_all(&[1, 4, b'd', b'd', b'0', b'4', 4, 0]).unwrap();
+            started.send(()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            f.write_all(&[0, 0, b'f', b'o', b'u', b'r']).unwrap();
+        })
+    };
+    start.recv().unwrap();
+    let store = LogStore::open(dir.path()).unwrap();
+    writer.join().unwrap();
+    assert_eq!(store.get("dd04").unwrap().unwrap(), b"four");
+}
+
+#[test]
+fn ledger_runs_on_log_backend() {
+    let dir = tempdir().unwrap();
+    std::env::set_var("VAULTMESH_LEDGER_DIR", dir.path());
+    std::env::set_var("VAULTMESH_LEDGER_BACKEND", "log");
+
+    let d = ledger::add_json("", b"{\"hello\": 1}", None, None).unwrap();
+    assert_eq!(ledger::get_json(&d).unwrap(), b"{\"hello\":1}");
+    assert!(dir.path().join("objects.log").exists());
+    assert!(!dir.path().join(format!("{d}.json")).exists());
+    assert_eq!(ledger::list().unwrap().len(), 1);
+}
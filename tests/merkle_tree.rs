@@ -0,0 +1,189 @@
+mod common;
+
+use common::{ops_receipt, sign_ops};
+use vaultmesh::merkle::{self, Alg, PathStep, Side};
+
+fn leaves(n: usize) -> Vec<String> {
+    (0..n)
+        .map(|i| hex::encode(blake3::hash(format!("leaf-{i}").as_bytes()).as_bytes()))
+        .collect()
+}
+
+#[test]
+fn every_audit_path_folds_to_root() {
+    for alg in [Alg::SortedPair, Alg::Rfc6962] {
+        for n in 1..=13 {
+            let ls = leaves(n);
+            let root = merkle::root(alg, &ls).unwrap();
+            for (i, leaf) in ls.iter().enumerate() {
+                let path = merkle::audit_path(alg, &ls, i).unwrap().unwrap();
+                assert!(
+                    merkle::verify_path(alg, leaf, &path, &root).unwrap(),
+                    "{alg} n={n} i={i}"
+                );
+            }
+            assert!(merkle::audit_path(alg, &ls, n).unwrap().is_none());
+        }
+    }
+}
+
+#[test]
+fn rfc6962_paths_are_positional_and_position_matters() {
+    let ls = leaves(5);
+    let root = merkle::root(Alg::Rfc6962, &ls).unwrap();
+    let mut path = merkle::audit_path(Alg::Rfc6962, &ls, 0).unwrap().unwrap();
+    assert!(matches!(
+        path[0],
+        PathStep::Positional {
+            side: Side::Right,
+            ..
+        }
+    ));
+    if let PathStep::Positional { side, .. } = &mut path[0] {
+        *side = Side::Left;
+    }
+    assert!(!merkle::verify_path(Alg::Rfc6962, &ls[0], &path, &root).unwrap());
+}
+
+#[test]
+fn rfc6962_does_not_duplicate_odd_leaves() {
+    // With duplication, [a, b, c] and [a, b, c, c] would share a root.
+    let mut ls = leaves(3);
+    let r3 = merkle::root(Alg::Rfc6962, &ls).unwrap();
+    ls.push(ls[2].clone());
+    let r4 = merkle::root(Alg::Rfc6962, &ls).unwrap();
+    assert_ne!(r3, r4);
+
+    let mut legacy = leaves(3);
+    let l3 = merkle::root(Alg::SortedPair, &legacy).unwrap();
+    legacy.push(legacy[2].clone());
+    assert_eq!(l3, merkle::root(Alg::SortedPair, &legacy).unwrap());
+}
+
+#[test]
+fn rfc6962_separates_leaf_and_node_domains() {
+    // An interior node presented as a leaf must not reproduce the parent hash.
+    let ls = leaves(2);
+    let root = merkle::root(Alg::Rfc6962, &ls).unwrap();
+    let single = merkle::root(Alg::Rfc6962, std::slice::from_ref(&root)).unwrap();
+    assert_ne!(single, root);
+    assert_ne!(merkle::root(Alg::SortedPair, &ls).unwrap(), root);
+}
+
+#[test]
+fn path_steps_round_trip_through_json() {
+    let legacy: Vec<PathStep> = serde_json::from_str(r#"["00ff", "ab01"]"#).unwrap();
+    assert_eq!(legacy[0], PathStep::Sibling("00ff".into()));
+    let positional: Vec<PathStep> =
+        serde_json::from_str(r#"[{"side":"left","hash":"00ff"}]"#).unwrap();
+    assert_eq!(
+        positional[0],
+        PathStep::Positional {
+            side: Side::Left,
+            hash: "00ff".into()
+        }
+    );
+    assert_eq!("rfc6962".parse::<Alg>().unwrap(), Alg::Rfc6962);
+    assert_eq!(
+        serde_json::to_string(&Alg::SortedPair).unwrap(),
+        "\"sorted-pair\""
+    );
+}
+
+#[test]
+fn consistency_proofs_verify_for_every_prefix() {
+    for n in 1..=17 {
+        let ls = leaves(n);
+        let new_root = merkle::root(Alg::Rfc6962, &ls).unwrap();
+        for m in 0..=n {
+            let old_root = merkle::root(Alg::Rfc6962, &ls[..m]).unwrap();
+            let proof = merkle::consistency_proof(&ls, m).unwrap();
+            assert!(
+                merkle::verify_consistency(m, n, &old_root, &new_root, &proof).unwrap(),
+                "m={m} n={n}"
+            );
+        }
+    }
+}
+
+#[test]
+fn consistency_detects_rewritten_history() {
+    let ls = leaves(7);
+    let old_root = merkle::root(Alg::Rfc6962, &ls[..4]).unwrap();
+
+    let mut rewritten = ls.clone();
+    rewritten[1] = leaves(9)[8].clone();
+    let new_root = merkle::root(Alg::Rfc6962, &rewritten).unwrap();
+    let proof = merkle::consistency_proof(&rewritten, 4).unwrap();
+    assert!(!merkle::verify_consistency(4, 7, &old_root, &new_root, &proof).unwrap());
+
+    // A shrinking tree is never consistent.
+    let root7 = merkle::root(Alg::Rfc6962, &ls).unwrap();
+    assert!(!merkle::verify_consistency(7, 4, &root7, &old_root, &[]).unwrap());
+    assert!(merkle::consistency_proof(&ls[..4], 7).is_err());
+
+    // An empty old tree only verifies under the empty-tree root.
+    let empty = merkle::root(Alg::Rfc6962, &[]).unwrap();
+    assert!(merkle::verify_consistency(0, 7, &empty, &root7, &[]).unwrap());
+    assert!(!merkle::verify_consistency(0, 7, &"ab".repeat(32), &root7, &[]).unwrap());
+    assert!(!merkle::verify_consistency(0, 7, "", &root7, &[]).unwrap());
+}
+
+#[test]
+fn frontier_tracks_rfc6962_root() {
+    let ls = leaves(33);
+    let mut frontier = merkle::Frontier::default();
+    assert_eq!(frontier.root(), merkle::root(Alg::Rfc6962, &[]).unwrap());
+    for n in 1..=ls.len() {
+        frontier.push(&ls[n - 1]).unwrap();
+        assert_eq!(frontier.size(), n as u64);
+        assert_eq!(
+            frontier.root(),
+            merkle::root(Alg::Rfc6962, &ls[..n]).unwrap(),
+            "n={n}"
+        );
+    }
+}
+
+#[test]
+fn rfc6962_seal_follows_receipt_id_order() {
+    let (home, work) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    let path = |name: &str| work.path().join(name).to_str().unwrap().to_string();
+    std::fs::create_dir(path("receipts")).unwrap();
+    let receipt = |id: &str, ts: &str| {
+        let mut rec = ops_receipt(&["alice"]);
+        rec.id = id.into();
+        rec.ts = ts.into();
+        sign_ops(rec)
+    };
+    // File names sort opposite to the receipts' ids; `early` and `tie` share
+    // an id and are ordered by `ts`.
+    let (late, early, tie) = (
+        receipt("01J00000000000000000000000", "2024-01-01T00:00:00Z"),
+        receipt("01H00000000000000000000000", "2024-01-02T00:00:00Z"),
+        receipt("01H00000000000000000000000", "2024-01-01T00:00:00Z"),
+    );
+    for (name, rec) in [("a", &late), ("b", &early), ("c", &tie)] {
+        let file = path(&format!("receipts/{name}.json"));
+        std::fs::write(&file, serde_json::to_vec(rec).unwrap()).unwrap();
+    }
+
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_vaultmesh"))
+        .args(["seal", "--date", "2025-01-01", "--alg", "rfc6962", "--dir"])
+        .arg(path("receipts"))
+        .arg("--out")
+        .arg(path("root.json"))
+        .env("HOME", home.path())
+        .env_remove("VM_ACTOR_KEY_PATH")
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let doc: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(path("root.json")).unwrap()).unwrap();
+    let want = merkle::root(Alg::Rfc6962, &[tie.leaf, early.leaf, late.leaf]).unwrap();
+    assert_eq!(doc["root"], want.as_str());
+}
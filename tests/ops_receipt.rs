@@ -0,0 +1,255 @@
+mod common;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use common::{get_json, ops_receipt, spawn_gateway};
+use std::process::Command;
+use tempfile::tempdir;
+use vaultmesh::ops::{self, LeafForm, Sign};
+use vaultmesh::{gateway, ledger, schema};
+
+#[test]
+fn ingest_checks_leaf_signature_and_approvals() {
+    let rec = ops_receipt(&["alice", "bob"]);
+    assert_eq!(rec.verify_for_ingest().unwrap(), LeafForm::V2);
+
+    let mut tampered = rec.clone();
+    tampered.op.target = "ws-dev".into();
+    assert!(tampered.verify_for_ingest().is_err());
+    // Recomputing the leaf does not help without a new signature.
+    tampered.leaf = ops::canonical_leaf_hex(&tampered);
+    assert!(tampered.verify_for_ingest().is_err());
+
+    assert!(ops_receipt(&["alice"]).verify_for_ingest().is_err());
+    assert!(ops_receipt(&["alice", " alice ", ""])
+        .verify_for_ingest()
+        .is_err());
+
+    let mut unsigned = rec;
+    unsigned.sign = Sign::none();
+    unsigned.leaf = ops::canonical_leaf_hex(&unsigned);
+    assert!(unsigned.verify_for_ingest().is_err());
+}
+
+#[test]
+fn version_selects_the_leaf_form() {
+    let rec = ops_receipt(&["alice", "bob"]);
+
+    // A declared version is not second-guessed by trying the other form.
+    let mut legacy = rec.clone();
+    legacy.version = Some("0.1".into());
+    legacy.leaf = ops::canonical_leaf_hex(&legacy);
+    assert_eq!(legacy.leaf_form(), None);
+    legacy.leaf = legacy.canonical_leaf().unwrap();
+    assert_eq!(legacy.leaf_form(), Some(LeafForm::Legacy));
+
+    let mut unknown = rec.clone();
+    unknown.version = Some("9.9".into());
+    assert!(unknown.canonical_leaf().is_err());
+    assert_eq!(unknown.leaf_form(), None);
+    let err = schema::validate_ops_receipt(&serde_json::to_value(&unknown).unwrap()).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("unsupported ops-receipt version 9.9"));
+
+    // Receipts from before versioning still match either form.
+    let mut unversioned = rec;
+    unversioned.version = None;
+    unversioned.leaf = ops::canonical_leaf_hex_legacy(&unversioned);
+    assert_eq!(unversioned.leaf_form(), Some(LeafForm::Legacy));
+}
+
+#[test]
+fn gateway_ingests_ops_receipts_into_the_ledger_tree() {
+    let dir = tempdir().unwrap();
+    std::env::set_var("VAULTMESH_LEDGER_DIR", dir.path());
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    // Unsigned pushes, as a `gateway --allow-unsigned` development node takes them.
+    let open = gateway::Options {
+        allow_unsigned: true,
+        ..Default::default()
+    };
+    let post = |v: serde_json::Value| {
+        rt.block_on(gateway::post_ops_receipt(
+            State(open.clone()),
+            HeaderMap::new(),
+            v.to_string().into(),
+        ))
+    };
+
+    let rec = ops_receipt(&["alice", "bob"]);
+    let Json(resp) = post(serde_json::to_value(&rec).unwrap()).unwrap();
+    let digest = resp["receipt_digest"].as_str().unwrap().to_string();
+    assert_eq!(resp["leaf"], rec.leaf);
+    assert_eq!(resp["count"], 1);
+
+    let entries = ledger::list().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].kind, ledger::OPS);
+    assert_eq!(entries[0].commit.as_deref(), Some("abc123"));
+    assert_eq!(
+        ledger::receipt_leaves().unwrap(),
+        std::slice::from_ref(&digest)
+    );
+    // The tree leaf is the stored document's digest, not the signed `leaf`,
+    // and the proof endpoint serves a path for exactly that digest.
+    assert_eq!(ledger::ops_digest(&rec).unwrap(), digest);
+    assert_ne!(rec.leaf, digest);
+    let proof = ledger::inclusion_proof(&digest).unwrap().unwrap();
+    assert_eq!(proof.digest, digest);
+    assert!(proof.verify().unwrap());
+    assert!(ledger::inclusion_proof(&rec.leaf).unwrap().is_none());
+    assert!(ledger::fsck().unwrap().is_ok());
+
+    // The CLI path stores the same receipt under the same digest, even when
+    // optional fields are laid out differently.
+    let mut sparse = serde_json::to_value(&rec).unwrap();
+    sparse["op"].as_object_mut().unwrap().remove("risk");
+    assert_eq!(
+        ledger::add_document(sparse.to_string().as_bytes()).unwrap(),
+        digest
+    );
+
+    let err = post(serde_json::to_value(ops_receipt(&["alice"])).unwrap()).unwrap_err();
+    assert_eq!(err.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Schema failures come back as a problem body naming the failing fields.
+    let mut bad = serde_json::to_value(&rec).unwrap();
+    bad["ts"] = serde_json::json!(7);
+    bad["op"].as_object_mut().unwrap().remove("plan_hash");
+    let err = post(bad).unwrap_err();
+    assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+    let resp = err.into_response();
+    assert_eq!(resp.headers()["content-type"], "application/problem+json");
+    let body = rt
+        .block_on(axum::body::to_bytes(resp.into_body(), usize::MAX))
+        .unwrap();
+    let problem: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(problem["status"], 400);
+    let mut failed: Vec<(String, String)> = problem["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| {
+            (
+                e["instance_path"].as_str().unwrap().to_string(),
+                e["keyword"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    failed.sort();
+    assert_eq!(
+        failed,
+        [
+            ("/op".to_string(), "required".to_string()),
+            ("/ts".to_string(), "type".to_string())
+        ]
+    );
+    assert_eq!(ledger::list().unwrap().len(), 1);
+    assert_eq!(ledger::list().unwrap().len(), 1);
+}
+
+#[test]
+fn cli_verifies_a_pushed_receipt_against_the_gateway_proof_and_sth() {
+    let (dir, home, work) = (tempdir().unwrap(), tempdir().unwrap(), tempdir().unwrap());
+    let gw = spawn_gateway(dir.path(), home.path(), &["--allow-unsigned"]);
+    let rec = ops_receipt(&["alice", "bob"]);
+    let body = ureq::post(&format!("{}/v1/receipts/ops", gw.url))
+        .set("content-type", "application/json")
+        .send_string(&serde_json::to_string(&rec).unwrap())
+        .unwrap()
+        .into_string()
+        .unwrap();
+    let resp: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let digest = resp["receipt_digest"].as_str().unwrap();
+
+    let write = |name: &str, v: &serde_json::Value| {
+        let path = work.path().join(name);
+        std::fs::write(&path, v.to_string()).unwrap();
+        path
+    };
+    let receipt = write("receipt.json", &serde_json::to_value(&rec).unwrap());
+    let proof = write(
+        "proof.json",
+        &get_json(&format!("{}/v1/proof/{digest}", gw.url)),
+    );
+    let sth = write("sth.json", &get_json(&format!("{}/v1/sth", gw.url)));
+    let verify = |root: &std::path::Path, proof: &std::path::Path| {
+        Command::new(env!("CARGO_BIN_EXE_vaultmesh"))
+            .arg("verify")
+            .arg("--receipt")
+            .arg(&receipt)
+            .arg("--root")
+            .arg(root)
+            .arg("--proof")
+            .arg(proof)
+            .arg("--strict")
+            .output()
+            .unwrap()
+    };
+    let out = verify(&sth, &proof);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(String::from_utf8_lossy(&out.stdout).contains("VERIFIED"));
+
+    // A tree head the proof was not made against is refused.
+    let mut other: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&sth).unwrap()).unwrap();
+    other["root"] = serde_json::json!("cc".repeat(32));
+    assert!(!verify(&write("other.json", &other), &proof)
+        .status
+        .success());
+    // So is a proof for a different digest.
+    let mut wrong: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&proof).unwrap()).unwrap();
+    wrong["digest"] = serde_json::json!(rec.leaf);
+    assert!(!verify(&sth, &write("wrong.json", &wrong)).status.success());
+}
+
+#[test]
+fn cli_finalizes_a_receipt_emitted_before_versioning() {
+    // As `receipt emit` wrote it before `version`, when no DID resolved.
+    let emitted = serde_json::json!({
+        "id": "01J0000000000000000000000A",
+        "ts": "2025-01-01T00:00:00Z",
+        "actor": { "id": "did:placeholder", "cap": ["tf:apply"], "sig": "" },
+        "op": {
+            "kind": "terraform", "target": "ws-prod", "risk": null, "change_window": null,
+            "approvals": ["alice", "bob"], "plan_hash": "aa".repeat(32), "apply_hash": ""
+        },
+        "build": { "repo": "org/infra", "commit": "abc", "binary_hash": "dev" },
+        "env": { "ci": null, "runner": null, "tf_version": null, "plugins": null },
+        "sign": { "alg": "none", "sig": "", "pub": "" },
+        "leaf": "cc".repeat(32),
+        "merkle": { "date": "", "path": [], "root": "" }
+    });
+    let work = tempdir().unwrap();
+    let (receipt, post, out) = (
+        work.path().join("receipt.json"),
+        work.path().join("post.tfstate"),
+        work.path().join("final.json"),
+    );
+    std::fs::write(&receipt, emitted.to_string()).unwrap();
+    std::fs::write(&post, "{}").unwrap();
+    let run = Command::new(env!("CARGO_BIN_EXE_vaultmesh"))
+        .args(["receipt", "finalize", "--receipt"])
+        .arg(&receipt)
+        .arg("--post")
+        .arg(&post)
+        .arg("--out")
+        .arg(&out)
+        .output()
+        .unwrap();
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    let finalized: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&out).unwrap()).unwrap();
+    assert_eq!(finalized["actor"]["id"], "did:placeholder");
+    schema::validate_ops_receipt(&finalized).expect("finalized legacy receipt valid");
+}
@@ -0,0 +1,330 @@
+mod common;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode},
+    Json,
+};
+use common::{keypair, signed_receipt};
+use tempfile::tempdir;
+use vaultmesh::ledger;
+use vaultmesh::sync::{peers, PeerInfo, TrustLevel};
+use vaultmesh::{gateway, httpsig, identity};
+
+fn bundle(digest: &str) -> serde_json::Value {
+    let r = signed_receipt(digest, 9);
+    serde_json::json!({
+        "receipt": r,
+        "provenance": {
+            "artifact": "artifact.bin",
+            "artifact_hash": digest,
+            "actor": {"id": "did:test:actor"},
+            "build": {},
+            "ci": {},
+            "ts": {"built": "2024-01-01T00:00:00Z"}
+        }
+    })
+}
+
+fn did(seed: u8) -> String {
+    identity::did_key_from_public(keypair(seed).public.as_bytes())
+}
+
+fn peer(seed: u8, trust: TrustLevel) -> PeerInfo {
+    PeerInfo {
+        id: did(seed),
+        url: format!("http://peer{seed}.example:8080"),
+        trust,
+    }
+}
+
+const FULL: u8 = 1;
+const READER: u8 = 2;
+const NEWCOMER: u8 = 3;
+const STRANGER: u8 = 4;
+
+/// Authority the gateway under test is configured with.
+const NODE: &str = "node.example:8080";
+
+fn signed_headers(seed: u8, path: &str, body: &[u8]) -> HeaderMap {
+    signed_for(NODE, seed, path, body)
+}
+
+fn signed_for(authority: &str, seed: u8, path: &str, body: &[u8]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in httpsig::sign("POST", authority, path, body, &keypair(seed)).pairs() {
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+    }
+    headers
+}
+
+#[test]
+#[allow(clippy::too_many_lines)]
+fn registry_drives_gateway_admission() {
+    let dir = tempdir().unwrap();
+    std::env::set_var("VAULTMESH_LEDGER_DIR", dir.path());
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let strict = gateway::Options {
+        authority: Some(NODE.into()),
+        ..Default::default()
+    };
+    let post = |opts: &gateway::Options, headers: HeaderMap, body: String| {
+        rt.block_on(gateway::post_verify(
+            State(opts.clone()),
+            headers,
+            body.into(),
+        ))
+    };
+    let push = |from: Option<u8>, body: serde_json::Value| {
+        let body = body.to_string();
+        let headers = from.map_or_else(HeaderMap::new, |seed| {
+            signed_headers(seed, "/v1/verify", body.as_bytes())
+        });
+        post(&strict, headers, body)
+    };
+    let status = |r: Result<Json<serde_json::Value>, gateway::ApiError>| r.unwrap_err().status();
+
+    // An empty registry admits nobody: unsigned pushes get 401, signed ones
+    // from unregistered DIDs 403.
+    assert_eq!(
+        status(push(None, bundle(&"a1".repeat(32)))),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(push(Some(STRANGER), bundle(&"a1".repeat(32)))),
+        StatusCode::FORBIDDEN
+    );
+    assert!(ledger::list().unwrap().is_empty());
+
+    // --allow-unsigned opens unsigned ingest, and nothing else.
+    let open = gateway::Options {
+        allow_unsigned: true,
+        ..strict.clone()
+    };
+    let unsigned = bundle(&"a1".repeat(32)).to_string();
+    let Json(ok) = post(&open, HeaderMap::new(), unsigned).unwrap();
+    assert_eq!(ok["status"], "verified");
+    let stranger = bundle(&"a7".repeat(32)).to_string();
+    let headers = signed_headers(STRANGER, "/v1/verify", stranger.as_bytes());
+    let r = post(&open, headers, stranger);
+    assert_eq!(status(r), StatusCode::FORBIDDEN);
+
+    peers::add(peer(FULL, TrustLevel::Full)).unwrap();
+    peers::add(peer(READER, TrustLevel::ReadOnly)).unwrap();
+    peers::add(peer(NEWCOMER, TrustLevel::Quarantine)).unwrap();
+    assert!(peers::add(peer(FULL, TrustLevel::Quarantine)).is_err());
+    // Only a decodable did:key can ever sign a request.
+    for id in [
+        "did:web:peer.example",
+        "did:test:actor",
+        "did:key:zNotBase58!",
+        "peer-1",
+    ] {
+        let bad = PeerInfo {
+            id: id.into(),
+            ..peer(STRANGER, TrustLevel::Full)
+        };
+        assert!(peers::add(bad).is_err(), "{id}");
+    }
+    assert_eq!(peers::load().unwrap().len(), 3);
+
+    assert_eq!(
+        status(push(None, bundle(&"a2".repeat(32)))),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(push(Some(STRANGER), bundle(&"a2".repeat(32)))),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        status(push(Some(READER), bundle(&"a2".repeat(32)))),
+        StatusCode::FORBIDDEN
+    );
+
+    // A registered peer's signature over a different body or path is refused.
+    let body = bundle(&"a2".repeat(32)).to_string();
+    let other = bundle(&"a9".repeat(32)).to_string();
+    let forged = signed_headers(FULL, "/v1/verify", other.as_bytes());
+    let r = post(&strict, forged, body.clone());
+    assert_eq!(status(r), StatusCode::UNAUTHORIZED);
+    let mut swapped = signed_headers(FULL, "/v1/verify", other.as_bytes());
+    let body_digest = signed_headers(FULL, "/v1/verify", body.as_bytes());
+    swapped.insert(
+        httpsig::CONTENT_DIGEST,
+        body_digest[httpsig::CONTENT_DIGEST].clone(),
+    );
+    let r = post(&strict, swapped, body.clone());
+    assert_eq!(status(r), StatusCode::UNAUTHORIZED);
+    let wrong_path = signed_headers(FULL, "/v1/receipts/ops", body.as_bytes());
+    let r = post(&strict, wrong_path, body.clone());
+    assert_eq!(status(r), StatusCode::UNAUTHORIZED);
+    // ... as is one addressed to another node, even if the Host header says so.
+    let mut elsewhere = signed_for("other.example:8080", FULL, "/v1/verify", body.as_bytes());
+    elsewhere.insert("host", HeaderValue::from_static("other.example:8080"));
+    let r = post(&strict, elsewhere, body.clone());
+    assert_eq!(status(r), StatusCode::UNAUTHORIZED);
+
+    // Each signed request is accepted once.
+    let a3 = bundle(&"a3".repeat(32)).to_string();
+    let headers = signed_headers(FULL, "/v1/verify", a3.as_bytes());
+    let Json(ok) = post(&strict, headers.clone(), a3.clone()).unwrap();
+    assert_eq!(ok["status"], "verified");
+    assert_eq!(ok["count"], 2);
+    let r = post(&strict, headers, a3);
+    assert_eq!(status(r), StatusCode::UNAUTHORIZED);
+
+    // Without a configured authority signed pushes are refused, even when
+    // the Host header names this node.
+    let mut by_host = signed_headers(FULL, "/v1/verify", body.as_bytes());
+    by_host.insert("host", HeaderValue::from_static(NODE));
+    let r = post(&gateway::Options::default(), by_host, body.clone());
+    assert_eq!(status(r), StatusCode::INTERNAL_SERVER_ERROR);
+    let headers = signed_headers(FULL, "/v1/verify", body.as_bytes());
+    let Json(ok) = post(&strict, headers, body).unwrap();
+    assert_eq!(ok["status"], "verified");
+
+    // Quarantined pushes are verified but kept out of the ledger.
+    let Json(staged) = push(Some(NEWCOMER), bundle(&"a4".repeat(32))).unwrap();
+    assert_eq!(staged["status"], "staged");
+    let digest = staged["receipt_digest"].as_str().unwrap();
+    assert_eq!(ledger::receipt_leaves().unwrap().len(), 3);
+    assert!(ledger::get_json(digest).is_err());
+    assert!(ledger::staging::get(digest).unwrap().is_some());
+    let log = ledger::staging::list().unwrap();
+    assert_eq!(log.len(), 2);
+    assert!(log.iter().all(|s| s.peer == did(NEWCOMER)));
+    assert_eq!(log[0].digest, digest);
+    assert!(ledger::fsck()
+        .unwrap()
+        .problems
+        .iter()
+        .all(|(_, p)| !matches!(p, ledger::Problem::Orphan)));
+
+    // Promotion and removal take effect on the next push.
+    peers::set_trust(&did(NEWCOMER), TrustLevel::Full).unwrap();
+    assert_eq!(
+        push(Some(NEWCOMER), bundle(&"a5".repeat(32))).unwrap()["status"],
+        "verified"
+    );
+    assert!(peers::remove(&did(FULL)).unwrap());
+    assert!(!peers::remove(&did(FULL)).unwrap());
+    assert_eq!(
+        status(push(Some(FULL), bundle(&"a6".repeat(32)))),
+        StatusCode::FORBIDDEN
+    );
+    assert!(peers::set_trust(&did(FULL), TrustLevel::Full).is_err());
+    assert_eq!(
+        peers::get(&did(READER)).unwrap().unwrap().trust,
+        TrustLevel::ReadOnly
+    );
+    assert_eq!(
+        "read-only".parse::<TrustLevel>().unwrap(),
+        TrustLevel::ReadOnly
+    );
+
+    // Concurrent edits are serialized, not lost.
+    let before = peers::load().unwrap().len();
+    std::thread::scope(|s| {
+        for seed in 10..18 {
+            s.spawn(move || peers::add(peer(seed, TrustLevel::Quarantine)).unwrap());
+        }
+    });
+    assert_eq!(peers::load().unwrap().len(), before + 8);
+}
+
+#[test]
+fn request_signatures_expire() {
+    let body = b"{}";
+    let kp = keypair(FULL);
+    let now = chrono::Utc::now().timestamp();
+    let check = |h: &httpsig::SignedHeaders| {
+        httpsig::verify("POST", NODE, "/v1/verify", body, |name| {
+            h.pairs()
+                .into_iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| v)
+        })
+    };
+    assert_eq!(
+        check(&httpsig::sign_at(
+            "POST",
+            NODE,
+            "/v1/verify",
+            body,
+            &kp,
+            now
+        ))
+        .unwrap(),
+        did(FULL)
+    );
+    let stale = httpsig::sign_at(
+        "POST",
+        NODE,
+        "/v1/verify",
+        body,
+        &kp,
+        now - httpsig::MAX_SKEW_SECS - 60,
+    );
+    assert!(check(&stale).is_err());
+    assert_eq!(
+        httpsig::path_of("http://node:8080/v1/verify?x=1"),
+        "/v1/verify"
+    );
+    assert_eq!(httpsig::path_of("http://node:8080"), "/");
+    assert_eq!(
+        httpsig::authority_of("http://node:8080/v1/verify?x=1"),
+        "node:8080"
+    );
+    assert_eq!(httpsig::authority_of("https://node?x=1"), "node");
+}
+
+#[test]
+fn accepted_nonces_survive_a_gateway_restart() {
+    let (dir, home) = (tempdir().unwrap(), tempdir().unwrap());
+    let vaultmesh = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_vaultmesh"))
+            .args(args)
+            .env("VAULTMESH_LEDGER_DIR", dir.path())
+            .env("HOME", home.path())
+            .output()
+            .unwrap()
+    };
+    let out = vaultmesh(&[
+        "peer",
+        "add",
+        &did(FULL),
+        "--url",
+        "http://peer1.example:8080",
+        "--trust",
+        "full",
+    ]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    // A gateway on every interface cannot tell which authority to check.
+    let out = vaultmesh(&["gateway", "--addr", "0.0.0.0:0"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--authority"));
+
+    let push = |url: &str, headers: &httpsig::SignedHeaders, body: &str| {
+        let mut req = ureq::post(&format!("{url}/v1/verify"));
+        for (name, value) in headers.pairs() {
+            req = req.set(name, value);
+        }
+        match req.send_string(body) {
+            Ok(r) => (r.status(), r.into_string().unwrap()),
+            Err(ureq::Error::Status(code, r)) => (code, r.into_string().unwrap()),
+            Err(e) => panic!("{e}"),
+        }
+    };
+    let body = bundle(&"b1".repeat(32)).to_string();
+    let headers = httpsig::sign("POST", NODE, "/v1/verify", body.as_bytes(), &keypair(FULL));
+    let gw = common::spawn_gateway(dir.path(), home.path(), &["--authority", NODE]);
+    assert_eq!(push(&gw.url, &headers, &body).0, 200);
+    drop(gw);
+
+    let gw = common::spawn_gateway(dir.path(), home.path(), &["--authority", NODE]);
+    let (code, text) = push(&gw.url, &headers, &body);
+    assert_eq!(code, 401);
+    assert!(text.contains("already used"), "{text}");
+    let fresh = httpsig::sign("POST", NODE, "/v1/verify", body.as_bytes(), &keypair(FULL));
+    assert_eq!(push(&gw.url, &fresh, &body).0, 200);
+}
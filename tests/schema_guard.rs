@@ -9,7 +9,7 @@ fn valid_receipt_json() -> serde_json::Value {
         "actor": { "id": "did:key:zTest" },
         "env":   { "ci":"github_actions", "git_commit":"abc", "git_ref":"refs/heads/main" },
         "ts":    "2025-01-01T00:00:00Z",
-        "subject": { "kind":"artifact", "digest":"deadbeef" }
+        "subject": { "kind":"artifact", "digest":"deadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef" }
     })
 }
 
@@ -17,7 +17,7 @@ fn valid_receipt_json() -> serde_json::Value {
 fn valid_provenance_json() -> serde_json::Value {
     json!({
         "artifact": "target/release/vaultmesh",
-        "artifact_hash": "deadbeef",
+        "artifact_hash": "deadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef",
         "actor": { "id":"did:key:zTest" },
         "build": { "repo":"org/repo", "commit":"abc", "ref":"refs/heads/main" },
         "ci": { "name":"github_actions", "url":"https://example/run/1", "runner":"r1" },
@@ -74,4 +74,298 @@ fn canonical_hash_is_stable() {
     assert_eq!(da, db, "canonical hash must ignore key order differences");
 }
 
+fn valid_ops_receipt_json() -> serde_json::Value {
+    json!({
+        "id": "01HZZZZZZZZZZZZZZZZZZZZZZZ",
+        "ts": "2025-01-01T00:00:00Z",
+        "actor": { "id":"did:key:zTest", "cap":["tf:apply"], "sig":"" },
+        "op": { "kind":"terraform", "target":"ws-prod", "plan_hash":"aa".repeat(32), "apply_hash":"" },
+        "build": { "repo":"org/infra", "commit":"abc", "binary_hash":"dev" },
+        "env": {},
+        "sign": { "alg":"none", "sig":"", "pub":"" },
+        "leaf": "cc".repeat(32),
+        "merkle": { "date":"", "path":[{ "side":"left", "hash":"dd".repeat(32) }], "root":"" }
+    })
+}
+
+#[test]
+fn ops_receipt_schema_guards_structure() {
+    schema::validate_ops_receipt(&valid_ops_receipt_json()).expect("ops receipt valid");
+
+    let mut v = valid_ops_receipt_json();
+    v["op"].as_object_mut().unwrap().remove("plan_hash");
+    let err = schema::validate_ops_receipt(&v).unwrap_err();
+    assert!(err.to_string().contains("ops receipt schema violation"));
+
+    let mut v = valid_ops_receipt_json();
+    v["merkle"]["path"] = json!([{ "side":"up", "hash":"dd".repeat(32) }]);
+    assert!(schema::validate_ops_receipt(&v).is_err());
+}
+
+#[test]
+fn root_schema_guards_structure() {
+    let root = "ab".repeat(32);
+    let ok = json!({ "date":"2025-01-01", "root":root, "count":2, "alg":"rfc6962" });
+    schema::validate_root(&ok).expect("root valid");
+
+    let err = schema::validate_root(&json!({ "date":"2025-01-01", "root":root, "count":-1 }))
+        .unwrap_err();
+    assert!(err.to_string().contains("root schema violation"));
+    assert!(schema::validate_root(&json!({ "date":"2025-01-01", "count":2 })).is_err());
+
+    // An empty sorted-pair tree has an empty root, but only with no leaves.
+    let empty = json!({ "date":"2025-01-01", "root":"", "count":0, "alg":"sorted-pair" });
+    schema::validate_root(&empty).expect("empty root valid");
+    assert!(schema::validate_root(&json!({ "date":"2025-01-01", "root":"", "count":1 })).is_err());
+}
+
+#[test]
+fn sealing_an_empty_dir_yields_a_valid_root() {
+    let (empty, dir, home) = (
+        tempfile::tempdir().unwrap(),
+        tempfile::tempdir().unwrap(),
+        tempfile::tempdir().unwrap(),
+    );
+    for alg in ["sorted-pair", "rfc6962"] {
+        let out = dir.path().join(format!("root-{alg}.json"));
+        let status = std::process::Command::new(env!("CARGO_BIN_EXE_vaultmesh"))
+            .args(["seal", "--date", "2025-01-01", "--alg", alg, "--dir"])
+            .arg(empty.path())
+            .arg("--out")
+            .arg(&out)
+            .env("HOME", home.path())
+            .env_remove("VM_ACTOR_KEY_PATH")
+            .status()
+            .unwrap();
+        assert!(status.success(), "{alg}");
+        let doc: serde_json::Value = serde_json::from_slice(&std::fs::read(&out).unwrap()).unwrap();
+        assert_eq!(doc["count"], 0);
+        assert_eq!(doc["sth"]["size"], 0);
+        schema::validate_root(&doc).expect(alg);
+    }
+}
+
+#[test]
+fn every_published_schema_is_printable() {
+    for name in schema::NAMES {
+        let s = schema::by_name(name).expect("published");
+        assert!(s["$id"]
+            .as_str()
+            .unwrap()
+            .ends_with(&format!("{name}.json")));
+    }
+    assert!(schema::by_name("nope").is_none());
+}
+
+#[test]
+fn violations_carry_instance_path_and_keyword() {
+    let mut v = valid_receipt_json();
+    v["ts"] = json!("not-a-datetime");
+    v["subject"].as_object_mut().unwrap().remove("digest");
+    let err = schema::validate_receipt(&v).unwrap_err();
+    assert_eq!(err.what, "receipt");
+    let mut failed: Vec<(&str, &str)> = err
+        .violations
+        .iter()
+        .map(|v| (v.instance_path.as_str(), v.keyword.as_str()))
+        .collect();
+    failed.sort_unstable();
+    assert_eq!(failed, [("/subject", "required"), ("/ts", "format")]);
+}
+
+// Set (or add) the value at `pointer`.
+fn with(mut v: serde_json::Value, pointer: &str, new: serde_json::Value) -> serde_json::Value {
+    let (parent, key) = pointer.rsplit_once('/').unwrap();
+    match v.pointer_mut(parent).expect(pointer) {
+        serde_json::Value::Array(items) => items[key.parse::<usize>().unwrap()] = new,
+        target => {
+            target[key] = new;
+        }
+    }
+    v
+}
 
+#[test]
+fn stricter_fields_accept_well_formed_values() {
+    let signed = with(
+        valid_receipt_json(),
+        "/sign",
+        json!({ "alg":"ed25519", "pub":"A".repeat(43) + "=", "sig":"A".repeat(86) + "==" }),
+    );
+    schema::validate_receipt(&signed).expect("signed receipt valid");
+    let did_web = with(
+        valid_receipt_json(),
+        "/actor/id",
+        json!("did:web:ci.example.com:users:alice%40example.com"),
+    );
+    schema::validate_receipt(&did_web).expect("did:web valid");
+    let finalized = with(
+        valid_ops_receipt_json(),
+        "/op/apply_hash",
+        json!("bb".repeat(32)),
+    );
+    schema::validate_ops_receipt(&finalized).expect("finalized ops receipt valid");
+}
+
+#[test]
+#[allow(clippy::too_many_lines)]
+fn reject_corpus() {
+    let r = valid_receipt_json;
+    let receipts = [
+        ("ts date only", with(r(), "/ts", json!("2025-01-01"))),
+        (
+            "ts month 13",
+            with(r(), "/ts", json!("2025-13-01T00:00:00Z")),
+        ),
+        (
+            "ts without offset",
+            with(r(), "/ts", json!("2025-01-01T00:00:00")),
+        ),
+        (
+            "short digest",
+            with(r(), "/subject/digest", json!("deadbeef")),
+        ),
+        (
+            "long digest",
+            with(r(), "/subject/digest", json!("ab".repeat(33))),
+        ),
+        (
+            "uppercase digest",
+            with(r(), "/subject/digest", json!("AB".repeat(32))),
+        ),
+        (
+            "non-hex digest",
+            with(r(), "/subject/digest", json!("zz".repeat(32))),
+        ),
+        ("actor not a DID", with(r(), "/actor/id", json!("alice"))),
+        ("DID without id", with(r(), "/actor/id", json!("did:key:"))),
+        (
+            "DID uppercase method",
+            with(r(), "/actor/id", json!("did:KEY:z6Mk")),
+        ),
+        (
+            "DID bad escape",
+            with(r(), "/actor/id", json!("did:web:a%zz")),
+        ),
+        (
+            "sign.pub not base64",
+            with(r(), "/sign", json!({ "pub":"not base64!", "sig":"AAAA" })),
+        ),
+        (
+            "sign.sig bad padding",
+            with(r(), "/sign", json!({ "pub":"AAAA", "sig":"AAA" })),
+        ),
+        (
+            "sign without sig",
+            with(r(), "/sign", json!({ "pub":"AAAA" })),
+        ),
+        (
+            "provenance_ref short digest",
+            with(
+                r(),
+                "/provenance_ref",
+                json!({ "path":"p.json", "digest":"ab" }),
+            ),
+        ),
+        ("unknown version", with(r(), "/version", json!("9"))),
+    ];
+    for (why, doc) in &receipts {
+        assert!(
+            schema::validate_receipt(doc).is_err(),
+            "receipt accepted: {why}"
+        );
+    }
+
+    let p = valid_provenance_json;
+    let provenance = [
+        (
+            "short artifact_hash",
+            with(p(), "/artifact_hash", json!("deadbeef")),
+        ),
+        (
+            "built not a date-time",
+            with(p(), "/ts/built", json!("yesterday")),
+        ),
+        ("actor not a DID", with(p(), "/actor/id", json!("ci-bot"))),
+    ];
+    for (why, doc) in &provenance {
+        assert!(
+            schema::validate_provenance(doc).is_err(),
+            "provenance accepted: {why}"
+        );
+    }
+
+    let o = valid_ops_receipt_json;
+    let ops = [
+        ("ts not a date-time", with(o(), "/ts", json!("1700000000"))),
+        ("actor not a DID", with(o(), "/actor/id", json!("ci-bot"))),
+        (
+            "versioned placeholder actor",
+            with(
+                with(o(), "/version", json!("0.2")),
+                "/actor/id",
+                json!("did:placeholder"),
+            ),
+        ),
+        ("short plan_hash", with(o(), "/op/plan_hash", json!("aa"))),
+        ("empty plan_hash", with(o(), "/op/plan_hash", json!(""))),
+        ("short apply_hash", with(o(), "/op/apply_hash", json!("bb"))),
+        ("short leaf", with(o(), "/leaf", json!("cc"))),
+        ("short prev", with(o(), "/prev", json!("ee"))),
+        (
+            "short path step",
+            with(o(), "/merkle/path/0/hash", json!("dd")),
+        ),
+        ("short merkle root", with(o(), "/merkle/root", json!("ff"))),
+        ("sign.sig not base64", with(o(), "/sign/sig", json!("%%%%"))),
+        ("sign.pub bad padding", with(o(), "/sign/pub", json!("A="))),
+        ("unknown version", with(o(), "/version", json!("0.3"))),
+    ];
+    for (why, doc) in &ops {
+        assert!(
+            schema::validate_ops_receipt(doc).is_err(),
+            "ops receipt accepted: {why}"
+        );
+    }
+
+    let root = json!({ "date":"2025-01-01", "root":"ab".repeat(32), "count":2 });
+    let sth = json!({
+        "alg":"rfc6962", "size":2, "root":"ab".repeat(32), "timestamp":"2025-01-01T00:00:00Z",
+        "node":"did:key:zTest", "sig":"AAAA"
+    });
+    schema::validate_root(&with(root.clone(), "/sth", sth.clone())).expect("root with sth valid");
+    let roots = [
+        (
+            "date not a date",
+            with(root.clone(), "/date", json!("01/01/2025")),
+        ),
+        ("short root", with(root.clone(), "/root", json!("ab"))),
+        (
+            "empty root over leaves",
+            with(root.clone(), "/root", json!("")),
+        ),
+        (
+            "empty sth root over leaves",
+            with(root.clone(), "/sth", with(sth.clone(), "/root", json!(""))),
+        ),
+        (
+            "sth bad timestamp",
+            with(
+                root.clone(),
+                "/sth",
+                with(sth.clone(), "/timestamp", json!("now")),
+            ),
+        ),
+        (
+            "sth sig not base64",
+            with(root.clone(), "/sth", with(sth.clone(), "/sig", json!("!!"))),
+        ),
+        (
+            "sth node not a DID",
+            with(root, "/sth", with(sth, "/node", json!("node-1"))),
+        ),
+    ];
+    for (why, doc) in &roots {
+        assert!(schema::validate_root(doc).is_err(), "root accepted: {why}");
+    }
+}
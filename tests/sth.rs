@@ -0,0 +1,28 @@
+mod common;
+
+use common::keypair;
+use vaultmesh::merkle::Alg;
+use vaultmesh::sth;
+
+#[test]
+fn signed_tree_head_verifies_and_binds_fields() {
+    let head = sth::sign(Alg::Rfc6962, 7, "ab", &keypair(3)).unwrap();
+    assert!(head.node.starts_with("did:key:z"));
+    head.verify().expect("fresh sth verifies");
+
+    let json = serde_json::to_string(&head).unwrap();
+    let parsed: sth::SignedTreeHead = serde_json::from_str(&json).unwrap();
+    parsed.verify().expect("round-tripped sth verifies");
+
+    let mut bigger = head.clone();
+    bigger.size = 8;
+    assert!(bigger.verify().is_err());
+
+    let mut other_root = head.clone();
+    other_root.root = "cd".into();
+    assert!(other_root.verify().is_err());
+
+    let mut other_node = head;
+    other_node.node = vaultmesh::identity::did_key_from_public(&[9u8; 32]);
+    assert!(other_node.verify().is_err());
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use common::{add_receipt, get_json, keypair, signed_receipt, spawn_gateway};
+use std::path::Path;
+use std::time::Duration;
+use tempfile::tempdir;
+use vaultmesh::ledger;
+use vaultmesh::sync::{daemon, peers, PeerInfo, TrustLevel};
+use vaultmesh::{identity, sth};
+
+#[test]
+fn daemon_pulls_due_peers_and_backs_off_failures() {
+    let remote = tempdir().unwrap();
+    let local = tempdir().unwrap();
+    let (remote_home, local_home) = (tempdir().unwrap(), tempdir().unwrap());
+    let use_ledger = |dir: &Path| std::env::set_var("VAULTMESH_LEDGER_DIR", dir);
+
+    use_ledger(remote.path());
+    add_receipt(&signed_receipt(&"aa".repeat(32), 7));
+    let remote_gw = spawn_gateway(remote.path(), remote_home.path(), &[]);
+    let remote_url = remote_gw.url.clone();
+    let head: sth::SignedTreeHead =
+        serde_json::from_value(get_json(&format!("{remote_url}/v1/sth"))).unwrap();
+
+    use_ledger(local.path());
+    let down = identity::did_key_from_public(keypair(2).public.as_bytes());
+    let staged = identity::did_key_from_public(keypair(3).public.as_bytes());
+    for (id, url, trust) in [
+        (&head.node, remote_url.as_str(), TrustLevel::Full),
+        (&down, "http://127.0.0.1:1", TrustLevel::ReadOnly),
+        (&staged, "http://127.0.0.1:1", TrustLevel::Quarantine),
+    ] {
+        peers::add(PeerInfo {
+            id: id.clone(),
+            url: url.into(),
+            trust,
+        })
+        .unwrap();
+    }
+
+    let interval = Duration::from_mins(1);
+    daemon::sync_due(interval).unwrap();
+    let health = daemon::health().unwrap();
+    assert!(health.last_error.is_none());
+    let [ok, failing, quarantined] = health.peers.as_slice() else {
+        panic!("{health:?}");
+    };
+    assert_eq!((ok.fetched, ok.failures, ok.size), (1, 0, Some(1)));
+    assert_eq!((ok.head_size, ok.behind), (Some(1), Some(0)));
+    assert!(ok.last_sync.is_some() && ok.lag_secs.is_some_and(|s| s >= 0));
+    assert!(ok.last_error.is_none() && ok.next_attempt.is_none());
+    assert_eq!(ledger::list().unwrap().len(), 1);
+
+    assert_eq!(failing.failures, 1);
+    assert!(failing.last_error.is_some() && failing.next_attempt.is_some());
+    assert!(failing.last_sync.is_none());
+    // Quarantined peers are only pulled by hand.
+    assert!(quarantined.last_attempt.is_none());
+
+    // Still backing off: the failing peer is not retried yet.
+    daemon::sync_due(interval).unwrap();
+    assert_eq!(daemon::health().unwrap().peers[1].failures, 1);
+
+    // A round that cannot read the registry is recorded, not printed, and
+    // cleared by the next round that completes.
+    let registry = local.path().join("peers.jsonl");
+    let saved = std::fs::read(&registry).unwrap();
+    std::fs::write(&registry, "not json\n").unwrap();
+    daemon::record_round(daemon::sync_due(interval));
+    std::fs::write(&registry, saved).unwrap();
+    let failed = daemon::health().unwrap().last_error.expect("round error");
+    assert!(
+        failed.error.contains("bad peers.jsonl line"),
+        "{}",
+        failed.error
+    );
+    daemon::record_round(daemon::sync_due(interval));
+    assert!(daemon::health().unwrap().last_error.is_none());
+
+    assert_eq!(daemon::backoff(interval, 1), Duration::from_mins(2));
+    assert_eq!(daemon::backoff(interval, 20), daemon::MAX_BACKOFF);
+    let daily = Duration::from_hours(24);
+    assert_eq!(daemon::backoff(daily, 3), daily);
+
+    // A pull that fails after fetching the peer's tree head still reports
+    // how far behind it the mirror is.
+    use_ledger(remote.path());
+    let missing = add_receipt(&signed_receipt(&"cc".repeat(32), 7));
+    let object = remote
+        .path()
+        .join(&missing[..2])
+        .join(format!("{}.json", &missing[2..]));
+    let kept = std::fs::read(&object).unwrap();
+    std::fs::remove_file(&object).unwrap();
+    use_ledger(local.path());
+    daemon::sync_due(interval).unwrap();
+    let ok = &daemon::health().unwrap().peers[0];
+    assert_eq!(ok.failures, 1);
+    assert_eq!(
+        (ok.size, ok.head_size, ok.behind),
+        (Some(1), Some(2), Some(1))
+    );
+    std::fs::write(&object, kept).unwrap();
+
+    // The gateway runs the same loop with --sync-interval and serves /v1/peers.
+    use_ledger(remote.path());
+    add_receipt(&signed_receipt(&"bb".repeat(32), 7));
+    let local_gw = spawn_gateway(local.path(), local_home.path(), &["--sync-interval", "1s"]);
+    let local_url = local_gw.url.clone();
+    let mut served = serde_json::Value::Null;
+    for _ in 0..100 {
+        served = get_json(&format!("{local_url}/v1/peers"))["peers"].take();
+        if served[0]["fetched"] == 2 {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(served[0]["id"], head.node.as_str());
+    assert_eq!(served[0]["fetched"], 2);
+    assert_eq!(served[0]["size"], 3);
+    assert_eq!(served[0]["behind"], 0);
+    assert_eq!(served[1]["trust"], "read-only");
+    assert!(served[1]["last_error"].is_string());
+}
+
+#[test]
+fn sync_interval_parses_units() {
+    assert_eq!(
+        daemon::parse_interval("60s").unwrap(),
+        Duration::from_mins(1)
+    );
+    assert_eq!(
+        daemon::parse_interval("5m").unwrap(),
+        Duration::from_mins(5)
+    );
+    assert_eq!(
+        daemon::parse_interval("1h").unwrap(),
+        Duration::from_hours(1)
+    );
+    assert_eq!(
+        daemon::parse_interval("90").unwrap(),
+        Duration::from_secs(90)
+    );
+    for bad in ["", "0s", "s", "1d", "-5m", "fast"] {
+        assert!(daemon::parse_interval(bad).is_err(), "{bad}");
+    }
+}
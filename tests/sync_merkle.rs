@@ -1,4 +1,5 @@
-use vaultmesh::sync::merkle_root;
+use vaultmesh::ledger::TREE_ALG;
+use vaultmesh::merkle::{self, Alg};
 
 #[test]
 fn merkle_root_is_stable_and_order_independent() {
@@ -14,9 +15,38 @@ fn merkle_root_is_stable_and_order_independent() {
     let mut b_sorted = b.clone();
     b_sorted.sort();
 
-    let r1 = merkle_root(&a);
-    let r2 = merkle_root(&b_sorted);
+    let r1 = merkle::root(Alg::SortedPair, &a).unwrap();
+    let r2 = merkle::root(Alg::SortedPair, &b_sorted).unwrap();
     assert_eq!(r1, r2);
     assert!(!r1.is_empty());
 }
 
+#[test]
+fn ledger_root_is_stable_and_order_dependent() {
+    let a: Vec<String> = ["00aa", "0102", "b3f1", "ffff"]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let mut b = a.clone();
+    b.reverse();
+
+    let r1 = merkle::root(TREE_ALG, &a).unwrap();
+    assert_eq!(r1, merkle::root(TREE_ALG, &a).unwrap());
+    assert_ne!(r1, merkle::root(TREE_ALG, &b).unwrap());
+    let mut swapped = a;
+    swapped.swap(0, 1);
+    assert_ne!(r1, merkle::root(TREE_ALG, &swapped).unwrap());
+}
+
+#[test]
+fn ledger_root_admits_inclusion_proofs() {
+    let digests: Vec<String> = ["00aa", "0102", "b3f1", "ffff", "abcd"]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let root = merkle::root(TREE_ALG, &digests).unwrap();
+    for (i, d) in digests.iter().enumerate() {
+        let path = merkle::audit_path(TREE_ALG, &digests, i).unwrap().unwrap();
+        assert!(merkle::verify_path(TREE_ALG, d, &path, &root).unwrap());
+    }
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use common::{add_receipt, get_json, signed_receipt, spawn_gateway};
+use std::path::Path;
+use tempfile::tempdir;
+use vaultmesh::ledger;
+use vaultmesh::sync::{peers, pull, PeerInfo, TrustLevel};
+
+/// Register the node serving `url` as a full peer.
+fn register(url: &str) {
+    let node = get_json(&format!("{url}/v1/sth"))["node"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    peers::add(PeerInfo {
+        id: node,
+        url: url.into(),
+        trust: TrustLevel::Full,
+    })
+    .unwrap();
+}
+
+#[test]
+fn pull_mirrors_peer_ledger_incrementally() {
+    let remote = tempdir().unwrap();
+    let local = tempdir().unwrap();
+    let home = tempdir().unwrap();
+    let use_ledger = |dir: &Path| std::env::set_var("VAULTMESH_LEDGER_DIR", dir);
+
+    use_ledger(remote.path());
+    let first = add_receipt(&signed_receipt(&"aa".repeat(32), 7));
+    let mut tampered = signed_receipt(&"bb".repeat(32), 7);
+    tampered.subject.kind = "tampered".into();
+    let bad = add_receipt(&tampered);
+    let gateway = spawn_gateway(remote.path(), home.path(), &[]);
+    let url = gateway.url.clone();
+
+    use_ledger(local.path());
+    // Only registered peers are pulled from, even with an empty registry.
+    let err = pull::pull(&url).unwrap_err();
+    assert!(err.to_string().contains("unknown peer"), "{err}");
+    assert!(ledger::list().unwrap().is_empty());
+    register(&url);
+    let report = pull::pull(&url).unwrap();
+    assert_eq!((report.fetched, report.present), (1, 0));
+    assert_eq!(report.rejected.len(), 1);
+    assert_eq!(report.rejected[0].0, bad);
+    assert_eq!(report.cursor.next, 2);
+    assert_eq!(report.cursor.size, 2);
+    assert!(!report.staged);
+    let mirrored: Vec<String> = ledger::list()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.digest)
+        .collect();
+    assert_eq!(mirrored, std::slice::from_ref(&first));
+    assert_eq!(
+        pull::cursor(&report.peer).unwrap().as_ref(),
+        Some(&report.cursor)
+    );
+
+    // Nothing new at the peer: the listing starts at the cursor and is empty.
+    let again = pull::pull(&url).unwrap();
+    assert_eq!((again.fetched, again.present), (0, 0));
+    assert!(again.rejected.is_empty());
+
+    use_ledger(remote.path());
+    let second = add_receipt(&signed_receipt(&"cc".repeat(32), 7));
+    // A receipt the peer lists as provenance is still checked as a receipt.
+    let mut relabeled = signed_receipt(&"cd".repeat(32), 7);
+    relabeled.subject.kind = "relabeled".into();
+    let mislabeled =
+        ledger::add_json("provenance", &serde_json::to_vec(&relabeled).unwrap(), None, None).unwrap();
+    use_ledger(local.path());
+    let more = pull::pull(&url).unwrap();
+    assert_eq!((more.fetched, more.present), (1, 0));
+    assert_eq!(more.rejected.len(), 1);
+    assert_eq!(more.rejected[0].0, mislabeled);
+    assert!(more.rejected[0].1.contains("signature"), "{:?}", more.rejected);
+    assert_eq!(more.cursor.next, 4);
+    let mirrored: Vec<String> = ledger::list()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.digest)
+        .collect();
+    assert_eq!(mirrored, [first, second]);
+
+    // Pulls from different peers at once keep both cursors.
+    let other = tempdir().unwrap();
+    let other_home = tempdir().unwrap();
+    use_ledger(other.path());
+    add_receipt(&signed_receipt(&"ab".repeat(32), 7));
+    let other_gw = spawn_gateway(other.path(), other_home.path(), &[]);
+    use_ledger(local.path());
+    register(&other_gw.url);
+    let latest = std::thread::scope(|s| {
+        let ours = s.spawn(|| pull::pull(&url).unwrap());
+        s.spawn(|| pull::pull(&other_gw.url).unwrap());
+        ours.join().unwrap()
+    });
+    assert_eq!(pull::cursors().unwrap().len(), 2);
+    assert_eq!(ledger::list().unwrap().len(), 3);
+
+    // The same node key serving a different history is refused, and the
+    // cursor stays at the last head it proved.
+    drop(gateway);
+    let forked = tempdir().unwrap();
+    use_ledger(forked.path());
+    for d in ["dd", "ee", "ff", "11"] {
+        add_receipt(&signed_receipt(&d.repeat(32), 7));
+    }
+    let fork = spawn_gateway(forked.path(), home.path(), &[]);
+    use_ledger(local.path());
+    let err = pull::pull(&fork.url).unwrap_err();
+    assert!(err.to_string().contains("forked"), "{err}");
+    assert_eq!(
+        pull::cursor(&latest.peer).unwrap().as_ref(),
+        Some(&latest.cursor)
+    );
+    assert_eq!(ledger::list().unwrap().len(), 3);
+}
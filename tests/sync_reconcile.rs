@@ -0,0 +1,105 @@
+use std::cell::Cell;
+use std::sync::Arc;
+use vaultmesh::ledger::{self, Entry};
+use vaultmesh::sync::reconcile::{self, Range, LEAF_MAX};
+
+fn entry(i: u64) -> Entry {
+    Entry {
+        seq: i,
+        digest: blake3::hash(&i.to_le_bytes()).to_hex().to_string(),
+        kind: "receipt".into(),
+        ts: "2025-01-01T00:00:00.000Z".into(),
+        commit: None,
+        git_ref: None,
+    }
+}
+
+fn digests(entries: &[Entry]) -> Vec<&str> {
+    let mut d: Vec<&str> = entries.iter().map(|e| e.digest.as_str()).collect();
+    d.sort_unstable();
+    d
+}
+
+#[test]
+fn reconcile_finds_symmetric_difference_in_few_rounds() {
+    // 5000 shared entries; 3 only at the peer, 2 only here.
+    let shared = 0..5000;
+    let local = reconcile::sorted(shared.clone().chain([9001, 9002]).map(entry).collect());
+    let remote = reconcile::sorted(shared.chain([7001, 7002, 7003]).map(entry).collect());
+
+    let sent = Cell::new(0);
+    let diff = reconcile::diff_with(&local, |prefixes| {
+        let ranges = reconcile::ranges(&remote, prefixes)?;
+        let listed: usize = ranges
+            .iter()
+            .map(|r| r.entries.as_ref().map_or(0, Vec::len))
+            .sum();
+        sent.set(sent.get() + listed);
+        Ok(ranges)
+    })
+    .unwrap();
+
+    let expected_remote: Vec<Entry> = [7001, 7002, 7003].map(entry).into();
+    let expected_local: Vec<Entry> = [9001, 9002].map(entry).into();
+    assert_eq!(digests(&diff.remote_only), digests(&expected_remote));
+    assert_eq!(digests(&diff.local_only), digests(&expected_local));
+    // Root, then one level per hex digit until ranges hold <= LEAF_MAX digests.
+    assert!(diff.rounds <= 4, "took {} rounds", diff.rounds);
+    assert!(
+        sent.get() <= 5 * 16 * LEAF_MAX,
+        "listed {} entries",
+        sent.get()
+    );
+}
+
+#[test]
+fn identical_ledgers_agree_in_one_round() {
+    let entries = reconcile::sorted((0..1000).map(entry).collect());
+    let diff =
+        reconcile::diff_with(&entries, |prefixes| reconcile::ranges(&entries, prefixes)).unwrap();
+    assert_eq!(diff.rounds, 1);
+    assert!(diff.remote_only.is_empty() && diff.local_only.is_empty());
+
+    let empty =
+        reconcile::diff_with(&[], |prefixes| reconcile::ranges(&entries, prefixes)).unwrap();
+    assert_eq!(empty.remote_only.len(), 1000);
+}
+
+#[test]
+fn ranges_reject_bad_prefixes() {
+    let entries = reconcile::sorted((0..10).map(entry).collect());
+    let ok = reconcile::ranges(&entries, &[String::new()]).unwrap();
+    assert_eq!(ok[0].count, 10);
+    assert_eq!(ok[0].entries.as_ref().map(Vec::len), Some(10));
+    for bad in ["AB", "xyz", &"a".repeat(65)] {
+        assert!(
+            reconcile::ranges(&entries, &[bad.to_string()]).is_err(),
+            "{bad}"
+        );
+    }
+    let too_many = vec![String::new(); reconcile::MAX_PREFIXES + 1];
+    assert!(reconcile::ranges(&entries, &too_many).is_err());
+    // A peer answering for prefixes it was not asked about is an error.
+    let wrong = |_: &[String]| -> anyhow::Result<Vec<Range>> {
+        reconcile::ranges(&entries, &["0".to_string()])
+    };
+    assert!(reconcile::diff_with(&entries, wrong).is_err());
+}
+
+#[test]
+fn sorted_digests_are_shared_until_the_index_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    std::env::set_var("VAULTMESH_LEDGER_DIR", dir.path());
+    for i in 0..20u8 {
+        ledger::add_json("", format!("{{\"n\":{i}}}").as_bytes(), None, None).unwrap();
+    }
+    let first = ledger::sorted_by_digest().unwrap();
+    assert_eq!(*first, reconcile::sorted(ledger::list().unwrap()));
+    assert!(Arc::ptr_eq(&first, &ledger::sorted_by_digest().unwrap()));
+
+    let added = ledger::add_json("", b"{\"n\":20}", None, None).unwrap();
+    let second = ledger::sorted_by_digest().unwrap();
+    assert!(!Arc::ptr_eq(&first, &second));
+    assert_eq!(second.len(), 21);
+    assert!(second.iter().any(|e| e.digest == added));
+}
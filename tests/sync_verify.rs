@@ -0,0 +1,106 @@
+mod common;
+
+use common::{add_receipt, get_json, signed_receipt, spawn_gateway};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::tempdir;
+use vaultmesh::ledger::{InclusionProof, TREE_ALG};
+use vaultmesh::merkle;
+use vaultmesh::sync::{peers, PeerInfo, TrustLevel};
+
+fn sync_verify(url: &str, digest: &str, ledger: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_vaultmesh"))
+        .args(["sync", "verify", url, "--digest", digest])
+        .env("VAULTMESH_LEDGER_DIR", ledger)
+        .output()
+        .unwrap()
+}
+
+/// Serve `bodies`, keyed by request path, on a free port.
+fn spawn_fake_peer(bodies: HashMap<String, String>) -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    listener.set_nonblocking(true).unwrap();
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let app = axum::Router::new().fallback(move |uri: axum::http::Uri| {
+                let body = bodies.get(uri.path()).cloned();
+                async move { body.ok_or(axum::http::StatusCode::NOT_FOUND) }
+            });
+            let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+            axum::serve(listener, app).await.unwrap();
+        });
+    });
+    url
+}
+
+#[test]
+fn verify_requires_the_proof_to_match_the_signed_tree_head() {
+    let (remote, home, local) = (tempdir().unwrap(), tempdir().unwrap(), tempdir().unwrap());
+    std::env::set_var("VAULTMESH_LEDGER_DIR", remote.path());
+    let digest = add_receipt(&signed_receipt(&"aa".repeat(32), 7));
+    add_receipt(&signed_receipt(&"bb".repeat(32), 7));
+    let gw = spawn_gateway(remote.path(), home.path(), &[]);
+
+    let out = sync_verify(&gw.url, &digest, local.path());
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    // A peer that serves a path folding to a root it never signed: the
+    // one-leaf tree over the receipt, while its signed head covers two.
+    let forged = InclusionProof {
+        digest: digest.clone(),
+        alg: TREE_ALG,
+        root: merkle::root(TREE_ALG, std::slice::from_ref(&digest)).unwrap(),
+        size: 1,
+        index: 0,
+        path: Vec::new(),
+    };
+    assert!(forged.verify().unwrap());
+    let fetch = |path: &str| {
+        ureq::get(&format!("{}{path}", gw.url))
+            .call()
+            .unwrap()
+            .into_string()
+            .unwrap()
+    };
+    let liar = spawn_fake_peer(HashMap::from([
+        (
+            format!("/v1/ledger/{digest}"),
+            fetch(&format!("/v1/ledger/{digest}")),
+        ),
+        ("/v1/sth".to_string(), fetch("/v1/sth")),
+        (
+            format!("/v1/proof/{digest}"),
+            serde_json::to_string(&forged).unwrap(),
+        ),
+    ]));
+    let out = sync_verify(&liar, &digest, local.path());
+    assert!(!out.status.success());
+    let err = String::from_utf8_lossy(&out.stderr);
+    assert!(err.contains("not against the tree head"), "{err}");
+
+    // A registered URL must serve heads signed by the registered DID.
+    std::env::set_var("VAULTMESH_LEDGER_DIR", local.path());
+    let node = get_json(&format!("{}/v1/sth", gw.url))["node"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let other = vaultmesh::identity::did_key_from_public(common::keypair(9).public.as_bytes());
+    assert_ne!(node, other);
+    peers::add(PeerInfo {
+        id: other,
+        url: gw.url.clone(),
+        trust: TrustLevel::Full,
+    })
+    .unwrap();
+    let out = sync_verify(&gw.url, &digest, local.path());
+    assert!(!out.status.success());
+    let err = String::from_utf8_lossy(&out.stderr);
+    assert!(err.contains(&format!("signs as {node}")), "{err}");
+}